@@ -362,6 +362,7 @@ impl Allowed {
                 ("gifs", &[PrimClass::Sys(SysOpClass::Gifs)]),
                 ("audio", &[PrimClass::Sys(SysOpClass::Audio)]),
                 ("tcp", &[PrimClass::Sys(SysOpClass::Tcp)]),
+                ("data", &[PrimClass::Sys(SysOpClass::Data)]),
                 ("env", &[PrimClass::Sys(SysOpClass::Env)]),
                 ("command", &[PrimClass::Sys(SysOpClass::Command)]),
                 ("filesystem", &[PrimClass::Sys(SysOpClass::Filesystem)]),
@@ -479,6 +480,7 @@ impl Allowed {
                         SysOpClass::Images => ("System - Images".into_view(), "Work with static images"),
                         SysOpClass::Gifs => ("System - GIFs".into_view(), "Work with animated GIFs"),
                         SysOpClass::Tcp => ("System - TCP".into_view(), "Work with TCP sockets"),
+                        SysOpClass::Data => ("System - Data".into_view(), "Encode and decode data formats"),
                         SysOpClass::Misc => ("System - Misc".into_view(), ""),
                     }
                 }
@@ -642,6 +642,7 @@ fn init_rt() -> Uiua {
     Uiua::with_backend(WebBackend::default())
         .with_mode(RunMode::All)
         .with_execution_limit(Duration::from_secs_f64(get_execution_limit()))
+        .with_memory_limit(512 << 20)
 }
 
 fn just_values(code: &str) -> UiuaResult<Vec<Value>> {
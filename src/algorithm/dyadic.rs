@@ -199,6 +199,10 @@ impl<T: ArrayValue> Array<T> {
 impl Value {
     /// `join` the array with another
     pub fn join(self, other: Self, env: &Uiua) -> UiuaResult<Self> {
+        env.check_alloc_bytes(
+            [self.element_count().saturating_add(other.element_count())],
+            self.element_size().max(other.element_size()),
+        )?;
         self.join_impl(other, env)
     }
     /// `join` the array with another
@@ -655,6 +659,8 @@ impl Value {
             count += 1;
             if count == 2 {
                 let total_elements = total_rows * value.shape().iter().product::<usize>();
+                let bytes = total_elements.saturating_mul(value.element_size());
+                ctx.check_memory_in_use(ctx.memory_in_use().saturating_add(bytes))?;
                 value.reserve_min(total_elements);
                 value.couple_impl(row, ctx)?;
             } else {
@@ -712,6 +718,7 @@ impl Value {
     /// `reshape` this value with another
     pub fn reshape(&mut self, shape: &Self, env: &Uiua) -> UiuaResult {
         if let Ok(n) = shape.as_nat(env, "") {
+            env.check_alloc_bytes([n, self.element_count()], self.element_size())?;
             match self {
                 Value::Num(a) => a.reshape_scalar(n),
                 #[cfg(feature = "bytes")]
@@ -830,6 +837,7 @@ impl<T: ArrayValue> Array<T> {
                 return Err(env.error(format!("Cannot reshape array with {n} negative dimensions")))
             }
         };
+        env.check_alloc::<T>(shape.iter().copied())?;
         let target_len: usize = shape.iter().product();
         if self.data.len() < target_len {
             if let Some(fill) = env.fill::<T>() {
@@ -949,6 +957,12 @@ impl Value {
             "Keep amount must be a natural number \
             or list of natural numbers",
         )?;
+        let total = (counts.iter()).fold(0usize, |acc, &n| acc.saturating_add(n));
+        if self.rank() == 0 {
+            env.check_alloc_bytes([total, kept.element_count()], kept.element_size())?;
+        } else {
+            env.check_alloc_bytes([total, kept.row_len()], kept.element_size())?;
+        }
         Ok(if self.rank() == 0 {
             match kept {
                 Value::Num(a) => a.scalar_keep(counts[0]).into(),
@@ -1393,6 +1407,9 @@ impl Value {
             return Err(env.error("Cannot take from scalar"));
         }
         let index = self.as_ints(env, "Index must be a list of integers")?;
+        let taken = (index.iter().map(|i| i.unsigned_abs()))
+            .chain(from.shape().iter().skip(index.len()).copied());
+        env.check_alloc_bytes(taken, from.element_size())?;
         Ok(match from {
             Value::Num(a) => Value::Num(a.take(&index, env)?),
             #[cfg(feature = "bytes")]
@@ -1886,6 +1903,7 @@ impl Value {
     /// Use this value to `select` from another
     pub fn select(&self, from: &Self, env: &Uiua) -> UiuaResult<Self> {
         let (indices_shape, indices_data) = self.as_shaped_indices(env)?;
+        env.check_alloc_bytes([indices_data.len(), from.row_len()], from.element_size())?;
         Ok(match from {
             Value::Num(a) => a.select_impl(indices_shape, &indices_data, env)?.into(),
             #[cfg(feature = "bytes")]
@@ -2109,6 +2127,7 @@ impl<T: ArrayValue> Array<T> {
         new_shape.extend(self.shape.iter().zip(&size_spec).map(|(a, b)| a + 1 - *b));
         new_shape.extend_from_slice(&size_spec);
         new_shape.extend_from_slice(&self.shape[size_spec.len()..]);
+        env.check_alloc::<T>(new_shape.iter().copied())?;
         // Check if the window size is too large
         for (size, sh) in size_spec.iter().zip(&self.shape) {
             if *size > *sh {
@@ -52,6 +52,8 @@ fn prim_inverse(prim: Primitive, span: usize) -> Option<Instr> {
         Utf => Instr::ImplPrim(InvUtf, span),
         Parse => Instr::ImplPrim(InvParse, span),
         Fix => Instr::ImplPrim(InvFix, span),
+        Sys(SysOp::JsonDecode) => Instr::Prim(Sys(SysOp::JsonEncode), span),
+        Sys(SysOp::JsonEncode) => Instr::Prim(Sys(SysOp::JsonDecode), span),
         _ => return None,
     })
 }
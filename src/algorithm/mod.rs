@@ -17,7 +17,7 @@ use crate::{
 mod dyadic;
 pub(crate) mod invert;
 pub mod loops;
-mod monadic;
+pub(crate) mod monadic;
 pub mod pervade;
 pub mod reduce;
 pub mod table;
@@ -61,6 +61,8 @@ pub trait FillContext {
     fn fill<T: ArrayValue>(&self) -> Option<T>;
     fn fill_error(error: Self::Error) -> Self::Error;
     fn is_fill_error(error: &Self::Error) -> bool;
+    fn memory_in_use(&self) -> usize;
+    fn check_memory_in_use(&self, in_use: usize) -> Result<(), Self::Error>;
 }
 
 impl FillContext for Uiua {
@@ -80,6 +82,12 @@ impl FillContext for Uiua {
     fn is_fill_error(error: &Self::Error) -> bool {
         error.is_fill()
     }
+    fn memory_in_use(&self) -> usize {
+        self.memory_in_use()
+    }
+    fn check_memory_in_use(&self, in_use: usize) -> Result<(), Self::Error> {
+        self.check_memory_in_use(in_use)
+    }
 }
 
 impl FillContext for () {
@@ -99,6 +107,12 @@ impl FillContext for () {
     fn is_fill_error(error: &Self::Error) -> bool {
         match *error {}
     }
+    fn memory_in_use(&self) -> usize {
+        0
+    }
+    fn check_memory_in_use(&self, _: usize) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 pub(crate) fn shape_prefixes_match(a: &[usize], b: &[usize]) -> bool {
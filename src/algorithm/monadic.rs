@@ -1,11 +1,13 @@
 //! Algorithms for monadic array operations
 
 use std::{
+    borrow::Cow,
     cmp::Ordering,
     collections::{BTreeMap, BTreeSet, HashMap},
     f64::consts::{PI, TAU},
     iter::{once, repeat},
     mem::size_of,
+    num::ParseFloatError,
     ptr,
 };
 
@@ -63,31 +65,37 @@ impl Value {
     }
     /// Attempt to parse the value into a number
     pub fn parse_num(&self, env: &Uiua) -> UiuaResult<Self> {
-        let mut s = self.as_string(env, "Parsed array must be a string")?;
-        if s.contains('¯') {
-            s = s.replace('¯', "-");
-        }
-        if s.contains('`') {
-            s = s.replace('`', "-");
-        }
-        if s.contains('η') {
-            s = s.replace('η', &(PI * 0.5).to_string());
-        }
-        if s.contains('π') {
-            s = s.replace('π', &PI.to_string());
-        }
-        if s.contains('τ') {
-            s = s.replace('τ', &TAU.to_string());
-        }
-        if s.contains('∞') {
-            s = s.replace('∞', &f64::INFINITY.to_string());
-        }
-        Ok(s.parse::<f64>()
+        let s = self.as_string(env, "Parsed array must be a string")?;
+        Ok(parse_number(&s)
             .map_err(|e| env.error(format!("Cannot parse into number: {}", e)))?
             .into())
     }
 }
 
+/// Parse a string into a number the way `parse` does
+pub(crate) fn parse_number(s: &str) -> Result<f64, ParseFloatError> {
+    let mut s = Cow::Borrowed(s);
+    if s.contains('¯') {
+        s = s.replace('¯', "-").into();
+    }
+    if s.contains('`') {
+        s = s.replace('`', "-").into();
+    }
+    if s.contains('η') {
+        s = s.replace('η', &(PI * 0.5).to_string()).into();
+    }
+    if s.contains('π') {
+        s = s.replace('π', &PI.to_string()).into();
+    }
+    if s.contains('τ') {
+        s = s.replace('τ', &TAU.to_string()).into();
+    }
+    if s.contains('∞') {
+        s = s.replace('∞', &f64::INFINITY.to_string()).into();
+    }
+    s.parse::<f64>()
+}
+
 impl<T: ArrayValue> Array<T> {
     /// Make the array 1-dimensional
     pub fn deshape(&mut self) {
@@ -109,11 +117,13 @@ impl Value {
             or a list of natural numbers",
         )?;
         if self.rank() == 0 {
+            env.check_alloc::<f64>([shape[0]])?;
             return Ok((0..shape[0]).collect());
         }
         if shape.is_empty() {
             return Ok(Array::<f64>::new(Shape::from_iter([0]), CowSlice::new()).into());
         }
+        env.check_alloc::<f64>(shape.iter().copied().chain([shape.len()]))?;
         let mut shape = Shape::from(shape.as_slice());
         let data = range(&shape, env)?;
         shape.push(shape.len());
@@ -652,6 +662,7 @@ impl Value {
         Ok(if self.rank() <= 1 {
             let counts = self.as_nats(env, "Argument to where must be an array of naturals")?;
             let total: usize = counts.iter().fold(0, |acc, &b| acc.saturating_add(b));
+            env.check_alloc::<f64>([total])?;
             let mut data = EcoVec::with_capacity(total);
             for (i, &b) in counts.iter().enumerate() {
                 for _ in 0..b {
@@ -664,6 +675,7 @@ impl Value {
             let counts =
                 self.as_natural_array(env, "Argument to where must be an array of naturals")?;
             let total: usize = counts.data.iter().fold(0, |acc, &b| acc.saturating_add(b));
+            env.check_alloc::<f64>([total, counts.rank()])?;
             let mut data = EcoVec::with_capacity(total);
             for (i, &b) in counts.data.iter().enumerate() {
                 for _ in 0..b {
@@ -23,6 +23,14 @@ pub fn table(env: &mut Uiua) -> UiuaResult {
     let f = env.pop_function()?;
     let xs = env.pop(1)?;
     let ys = env.pop(2)?;
+    env.check_alloc_bytes(
+        [
+            xs.row_count(),
+            ys.row_count(),
+            xs.row_len().max(ys.row_len()),
+        ],
+        xs.element_size().max(ys.element_size()),
+    )?;
     match (f.as_flipped_primitive(), xs, ys) {
         (Some((prim, flipped)), Value::Num(xs), Value::Num(ys)) => {
             if let Err((xs, ys)) = table_nums(prim, flipped, xs, ys, env) {
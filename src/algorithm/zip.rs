@@ -1,6 +1,6 @@
 //! Algorithms for zipping modifiers
 
-use std::sync::Arc;
+use std::{cell::Cell, sync::Arc};
 
 use crate::{
     algorithm::{
@@ -185,11 +185,15 @@ fn each1(f: Arc<Function>, xs: Value, env: &mut Uiua) -> UiuaResult {
         let mut new_values = multi_output(outputs, Vec::with_capacity(xs.element_count()));
         let new_shape = Shape::from(xs.shape());
         let mut old_values = xs.into_elements();
+        let mut in_use = env.memory_in_use();
         for val in old_values.by_ref() {
             env.push(val);
             env.call(f.clone())?;
             for i in 0..outputs {
-                new_values[i].push(env.pop("each's function result")?);
+                let value = env.pop("each's function result")?;
+                in_use += value.byte_size();
+                env.check_memory_in_use(in_use)?;
+                new_values[i].push(value);
             }
         }
         for new_values in new_values.into_iter().rev() {
@@ -226,6 +230,7 @@ fn each2(f: Arc<Function>, xs: Value, ys: Value, env: &mut Uiua) -> UiuaResult {
         let ys_shape = ys.shape().to_vec();
         let xs_values: Vec<_> = xs.into_elements().collect();
         let ys_values: Vec<_> = ys.into_elements().collect();
+        let in_use = Cell::new(env.memory_in_use());
         let (new_shape, new_values) = bin_pervade_generic(
             &xs_shape,
             xs_values,
@@ -236,9 +241,12 @@ fn each2(f: Arc<Function>, xs: Value, ys: Value, env: &mut Uiua) -> UiuaResult {
                 env.push(y);
                 env.push(x);
                 env.call(f.clone())?;
-                (0..outputs)
+                let values = (0..outputs)
                     .map(|_| env.pop("each's function result"))
-                    .collect::<Result<MultiOutput<_>, _>>()
+                    .collect::<Result<MultiOutput<_>, _>>()?;
+                in_use.set(in_use.get() + values.iter().map(Value::byte_size).sum::<usize>());
+                env.check_memory_in_use(in_use.get())?;
+                Ok(values)
             },
         )?;
         let mut transposed = multi_output(outputs, Vec::with_capacity(new_values.len()));
@@ -272,12 +280,16 @@ fn eachn(f: Arc<Function>, args: Vec<Value>, env: &mut Uiua) -> UiuaResult {
     let elem_count = args[0].element_count();
     let mut arg_elems: Vec<_> = args.into_iter().map(|v| v.into_elements()).collect();
     let mut new_values = Vec::new();
+    let mut in_use = env.memory_in_use();
     for _ in 0..elem_count {
         for arg in arg_elems.iter_mut().rev() {
             env.push(arg.next().unwrap());
         }
         env.call(f.clone())?;
-        new_values.push(env.pop("each's function result")?);
+        let value = env.pop("each's function result")?;
+        in_use += value.byte_size();
+        env.check_memory_in_use(in_use)?;
+        new_values.push(value);
     }
     let eached = Value::from_row_values(new_values, env)?;
     env.push(eached);
@@ -398,6 +410,7 @@ fn rowsn(f: Arc<Function>, args: Vec<Value>, env: &mut Uiua) -> UiuaResult {
     }
     let outputs = f.signature().outputs;
     let mut new_values = multi_output(outputs, Vec::new());
+    let mut in_use = env.memory_in_use();
     for _ in 0..row_count {
         for arg in arg_elems.iter_mut().rev() {
             match arg {
@@ -407,7 +420,10 @@ fn rowsn(f: Arc<Function>, args: Vec<Value>, env: &mut Uiua) -> UiuaResult {
         }
         env.call(f.clone())?;
         for i in 0..outputs {
-            new_values[i].push(env.pop("rows's function result")?);
+            let value = env.pop("rows's function result")?;
+            in_use += value.byte_size();
+            env.check_memory_in_use(in_use)?;
+            new_values[i].push(value);
         }
     }
     for new_values in new_values.into_iter().rev() {
@@ -7,6 +7,8 @@ use crate::value::Value;
 
 /// The element type for box arrays
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Boxed(pub Value);
 
 impl Boxed {
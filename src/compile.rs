@@ -12,7 +12,7 @@ use crate::{
     check::instrs_signature,
     function::*,
     lex::{CodeSpan, Sp, Span},
-    parse::{count_placeholders, ident_modifier_args},
+    parse::{binding_name_modifier_args_problem, count_placeholders},
     primitive::{ImplPrimitive, Primitive},
     run::{Global, RunMode},
     value::Value,
@@ -39,12 +39,14 @@ impl Uiua {
                 self.in_scope(|env| env.items(items.value, true))?;
             }
             Item::Words(words) => {
-                let can_run = match self.mode {
-                    RunMode::Normal => !in_test,
-                    RunMode::Test => in_test,
-                    RunMode::All => true,
-                };
-                if can_run || words_have_import(&words) {
+                let can_run = self.compile_only
+                    || match self.mode {
+                        RunMode::Normal => !in_test,
+                        RunMode::Test => in_test,
+                        RunMode::All => true,
+                    };
+                let has_import = words_have_import(&words);
+                if can_run || has_import {
                     let span = words
                         .first()
                         .unwrap()
@@ -57,15 +59,21 @@ impl Uiua {
                             .into());
                     }
                     let instrs = self.compile_words(words, true)?;
-                    self.exec_global_instrs(instrs)?;
+                    if !self.compile_only || has_import {
+                        self.exec_global_instrs(instrs)?;
+                    }
                 }
             }
             Item::Binding(binding) => {
-                let can_run = match self.mode {
-                    RunMode::Normal => !in_test,
-                    RunMode::All | RunMode::Test => true,
-                };
-                if can_run || words_have_import(&binding.words) {
+                let can_run = self.compile_only
+                    || match self.mode {
+                        RunMode::Normal => !in_test,
+                        RunMode::All | RunMode::Test => true,
+                    };
+                let has_import = words_have_import(&binding.words);
+                if self.compile_only && !has_import {
+                    self.bind_unevaluated(binding)?;
+                } else if can_run || has_import {
                     self.binding(binding)?;
                 }
             }
@@ -196,6 +204,55 @@ impl Uiua {
         }
         Ok(())
     }
+    /// Bind a name to its words as a function, without running them
+    ///
+    /// This is used to check code whose bindings cannot be run.
+    pub(crate) fn bind_unevaluated(&mut self, binding: Binding) -> UiuaResult {
+        let span = binding.name.span.clone();
+        let sig_declared = binding.signature.is_some();
+        let mut instrs = self.compile_words(binding.words, true)?;
+        let inferred = if let [Instr::PushFunc(f)] = instrs.as_slice() {
+            // Binding is a single inline function
+            let sig = f.signature();
+            instrs = f.instrs.clone();
+            Ok(sig)
+        } else {
+            instrs_signature(&instrs)
+        };
+        let sig = match (binding.signature, inferred) {
+            (Some(declared), Ok(inferred)) if declared.value != inferred => {
+                return Err(UiuaError::Run(Span::Code(declared.span.clone()).sp(
+                    format!(
+                        "Function signature mismatch:  declared {} but inferred {}",
+                        declared.value, inferred
+                    ),
+                )))
+            }
+            (Some(declared), _) => declared.value,
+            (None, Ok(sig)) => sig,
+            (None, Err(e)) => {
+                return Err(span
+                    .clone()
+                    .sp(format!("Cannot infer function signature: {e}"))
+                    .into())
+            }
+        };
+        let func = Function::new(FunctionId::Named(binding.name.value.clone()), instrs, sig);
+        self.compile_bind_function(binding.name.value, func.into(), sig_declared, span.into())
+    }
+    /// Get the signature of some words without running them
+    pub(crate) fn words_signature(&mut self, words: Vec<Sp<Word>>) -> Option<Signature> {
+        let instrs = self.compile_words(words, true).ok()?;
+        instrs_signature(&instrs).ok()
+    }
+    /// Get the signature of a word as a function without running it
+    pub(crate) fn word_function_signature(&mut self, word: Sp<Word>) -> Option<Signature> {
+        let instrs = self.compile_words(vec![word], false).ok()?;
+        match instrs.as_slice() {
+            [Instr::PushFunc(f)] => Some(f.signature()),
+            instrs => instrs_signature(instrs).ok(),
+        }
+    }
     pub(crate) fn compile_bind_value(
         &mut self,
         name: Ident,
@@ -229,21 +286,16 @@ impl Uiua {
     }
     fn validate_binding_name(&self, name: &Ident, instrs: &[Instr], span: Span) -> UiuaResult {
         let temp_function_count = count_temp_functions(instrs);
-        let name_marg_count = ident_modifier_args(name) as usize;
-        if temp_function_count != name_marg_count {
-            let trimmed = name.trim_end_matches('!');
-            let this = format!("{}{}", trimmed, "!".repeat(temp_function_count));
-            return Err(span
-                .clone()
-                .sp(format!(
-                    "The name {name} implies {name_marg_count} modifier arguments, \
-                    but the binding body references {temp_function_count}. Try `{this}`."
-                ))
-                .into());
+        if let Some(problem) = binding_name_modifier_args_problem(name, temp_function_count) {
+            return Err(span.clone().sp(problem).into());
         }
         Ok(())
     }
-    fn compile_words(&mut self, words: Vec<Sp<Word>>, call: bool) -> UiuaResult<Vec<Instr>> {
+    pub(crate) fn compile_words(
+        &mut self,
+        words: Vec<Sp<Word>>,
+        call: bool,
+    ) -> UiuaResult<Vec<Instr>> {
         self.new_functions.push(Vec::new());
         self.words(words, call)?;
         self.flush_diagnostics();
@@ -1247,7 +1299,7 @@ impl Uiua {
             ));
         }
     }
-    fn handle_primitive_experimental(&self, prim: Primitive, span: &CodeSpan) -> UiuaResult {
+    fn handle_primitive_experimental(&mut self, prim: Primitive, span: &CodeSpan) -> UiuaResult {
         if prim.is_experimental() && !self.scope.experimental {
             return Err(span
                 .clone()
@@ -1258,6 +1310,16 @@ impl Uiua {
                 ))
                 .into());
         }
+        if prim.is_experimental() && self.compile_only {
+            self.diagnostics.insert(Diagnostic::new(
+                format!(
+                    "{} is experimental and may change or be removed",
+                    prim.format()
+                ),
+                span.clone(),
+                DiagnosticKind::Advice,
+            ));
+        }
         Ok(())
     }
     fn primitive(&mut self, prim: Primitive, span: CodeSpan, call: bool) -> UiuaResult {
@@ -2,10 +2,13 @@ use std::{f64::consts::E, fmt, ops::*};
 
 /// Uiua's complex number type
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Complex {
     /// The real part
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::num"))]
     pub re: f64,
     /// The imaginary part
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::num"))]
     pub im: f64,
 }
 
@@ -0,0 +1,768 @@
+//! A Debug Adapter Protocol server over stdio
+//!
+//! The adapter launches a single program and drives it with a [`Stepper`].
+//! Requests are read on a separate thread and handled by the [`Debugger`] hook,
+//! which blocks while the program is paused.
+
+use std::{
+    any::Any,
+    fs,
+    io::{self, stdin, stdout, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, TryRecvError};
+use image::DynamicImage;
+use parking_lot::Mutex;
+use serde_json::{json, Value as Json};
+
+use crate::{
+    AudioStreamFn, CodeSpan, DebugAction, DebugState, Debugger, Handle, NativeSys, PauseReason,
+    Span, Stepper, SysBackend, TempStack, Uiua,
+};
+
+const THREAD_ID: i64 = 1;
+const STACK_REF: i64 = 1;
+
+/// Run a Debug Adapter Protocol server over stdio
+#[doc(hidden)]
+pub fn run_debug_adapter() {
+    serve(BufReader::new(stdin()), stdout());
+}
+
+/// Run a Debug Adapter Protocol server until the client disconnects
+fn serve(mut input: impl BufRead + Send + 'static, output: impl Write + Send + 'static) {
+    let (send, recv) = crossbeam_channel::unbounded();
+    let pending = Arc::new(AtomicBool::new(false));
+    let reader_pending = pending.clone();
+    thread::spawn(move || {
+        while let Ok(Some(message)) = read_message(&mut input) {
+            if send.send(message).is_err() {
+                break;
+            }
+            reader_pending.store(true, Ordering::Release);
+        }
+        // Let the running program see that the client is gone
+        drop(send);
+        reader_pending.store(true, Ordering::Release);
+    });
+    let out = Output::new(output);
+    let mut adapter = Adapter {
+        out,
+        recv,
+        pending,
+        stepper: Stepper::new(),
+        launch: None,
+        configured: false,
+        lines_start_at_1: true,
+        columns_start_at_1: true,
+        stop_on_entry: false,
+        terminated: Arc::new(AtomicBool::new(false)),
+        disconnected: Arc::new(AtomicBool::new(false)),
+    };
+    let out = adapter.out.clone();
+    let recv = adapter.recv.clone();
+    let terminated = adapter.terminated.clone();
+    let disconnected = adapter.disconnected.clone();
+    // Configure and launch the program
+    while !(adapter.configured && adapter.launch.is_some()) {
+        let Ok(request) = adapter.recv.recv() else {
+            return;
+        };
+        if adapter.handle_request(&request, None) == Control::Abort {
+            break;
+        }
+    }
+    if !terminated.load(Ordering::Relaxed) {
+        let (program, args) = adapter.launch.take().unwrap();
+        if adapter.stop_on_entry {
+            adapter.stepper.pause();
+        }
+        let mut env = Uiua::with_backend(DapSys { out: out.clone() })
+            .with_args(args)
+            .with_debugger(adapter);
+        let res = env.load_file(&program);
+        if !terminated.load(Ordering::Relaxed) {
+            let exit_code = match res {
+                Ok(()) => {
+                    for value in env.take_stack() {
+                        out.output("stdout", format!("{}\n", value.show()));
+                    }
+                    0
+                }
+                Err(e) => {
+                    out.output("stderr", format!("{e}\n"));
+                    1
+                }
+            };
+            for diagnostic in env.take_diagnostics() {
+                out.output("console", format!("{}\n", diagnostic.message));
+            }
+            out.event("exited", json!({ "exitCode": exit_code }));
+        }
+    }
+    if disconnected.load(Ordering::Relaxed) {
+        return;
+    }
+    out.event("terminated", json!({}));
+    // Wait for the client to disconnect
+    while let Ok(request) = recv.recv() {
+        match request["command"].as_str().unwrap_or_default() {
+            "disconnect" => {
+                out.respond(&request, json!({}));
+                break;
+            }
+            "terminate" => out.respond(&request, json!({})),
+            "threads" => out.respond(&request, json!({ "threads": [] })),
+            _ => out.respond_error(&request, "The program has exited"),
+        }
+    }
+}
+
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(len) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Message has no Content-Length",
+        ));
+    };
+    let mut content = vec![0; len];
+    reader.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)?))
+}
+
+/// Sends messages to the client
+#[derive(Clone)]
+struct Output {
+    seq: Arc<AtomicI64>,
+    writer: Arc<Mutex<dyn Write + Send>>,
+}
+
+impl Output {
+    fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            seq: Arc::new(AtomicI64::new(0)),
+            writer: Arc::new(Mutex::new(writer)),
+        }
+    }
+    fn send(&self, mut message: Json) {
+        message["seq"] = (self.seq.fetch_add(1, Ordering::Relaxed) + 1).into();
+        let content = message.to_string();
+        let mut writer = self.writer.lock();
+        _ = write!(writer, "Content-Length: {}\r\n\r\n{content}", content.len());
+        _ = writer.flush();
+    }
+    fn respond(&self, request: &Json, body: Json) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+    fn respond_error(&self, request: &Json, message: impl Into<String>) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message.into(),
+        }));
+    }
+    fn event(&self, event: &str, body: Json) {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+    fn output(&self, category: &str, output: impl Into<String>) {
+        self.event(
+            "output",
+            json!({ "category": category, "output": output.into() }),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    /// Keep handling requests
+    Wait,
+    /// Let the program run
+    Resume,
+    /// Stop the program
+    Abort,
+}
+
+struct Adapter {
+    out: Output,
+    recv: Receiver<Json>,
+    /// Set when a request arrives, so the running program doesn't have to poll for them
+    pending: Arc<AtomicBool>,
+    stepper: Stepper,
+    launch: Option<(PathBuf, Vec<String>)>,
+    configured: bool,
+    lines_start_at_1: bool,
+    columns_start_at_1: bool,
+    stop_on_entry: bool,
+    terminated: Arc<AtomicBool>,
+    disconnected: Arc<AtomicBool>,
+}
+
+impl Debugger for Adapter {
+    fn before_instr(&mut self, state: &DebugState) -> DebugAction {
+        if self.pending.swap(false, Ordering::Acquire) {
+            loop {
+                match self.recv.try_recv() {
+                    Ok(request) => {
+                        if self.handle_request(&request, Some(state)) == Control::Abort {
+                            return DebugAction::Abort;
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return DebugAction::Abort,
+                }
+            }
+        }
+        let Some(reason) = self.stepper.check(state) else {
+            return DebugAction::Continue;
+        };
+        let reason = match reason {
+            PauseReason::Breakpoint => "breakpoint",
+            PauseReason::Step => "step",
+            PauseReason::Pause if self.stop_on_entry => "entry",
+            PauseReason::Pause => "pause",
+        };
+        self.stop_on_entry = false;
+        self.out.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        );
+        loop {
+            let Ok(request) = self.recv.recv() else {
+                return DebugAction::Abort;
+            };
+            match self.handle_request(&request, Some(state)) {
+                Control::Wait => {}
+                Control::Resume => return DebugAction::Continue,
+                Control::Abort => return DebugAction::Abort,
+            }
+        }
+    }
+}
+
+impl Adapter {
+    fn handle_request(&mut self, request: &Json, state: Option<&DebugState>) -> Control {
+        let args = &request["arguments"];
+        let out = &self.out;
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                self.lines_start_at_1 = args["linesStartAt1"].as_bool().unwrap_or(true);
+                self.columns_start_at_1 = args["columnsStartAt1"].as_bool().unwrap_or(true);
+                out.respond(
+                    request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsTerminateRequest": true,
+                    }),
+                );
+                out.event("initialized", json!({}));
+            }
+            "launch" => {
+                let Some(program) = args["program"].as_str() else {
+                    out.respond_error(request, "No program to launch");
+                    return Control::Wait;
+                };
+                let program = fs::canonicalize(program).unwrap_or_else(|_| program.into());
+                let program_args = (args["args"].as_array().into_iter().flatten())
+                    .filter_map(|arg| arg.as_str().map(Into::into))
+                    .collect();
+                self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                self.launch = Some((program, program_args));
+                out.respond(request, json!({}));
+            }
+            "setBreakpoints" => {
+                let path = args["source"]["path"].as_str().map(Path::new);
+                let offset = if self.lines_start_at_1 { 0 } else { 1 };
+                let lines: Vec<usize> = (args["breakpoints"].as_array().into_iter().flatten())
+                    .filter_map(|bp| bp["line"].as_u64())
+                    .map(|line| line as usize + offset)
+                    .collect();
+                self.stepper.set_breakpoints(path, lines.iter().copied());
+                let breakpoints: Vec<Json> = (lines.iter())
+                    .map(|line| json!({ "verified": true, "line": line - offset }))
+                    .collect();
+                out.respond(request, json!({ "breakpoints": breakpoints }));
+            }
+            "setExceptionBreakpoints" => out.respond(request, json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                self.configured = true;
+                out.respond(request, json!({}));
+            }
+            "threads" => out.respond(
+                request,
+                json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+            ),
+            "stackTrace" => {
+                let frames: Vec<Json> = (state.map(DebugState::frames).into_iter().flatten())
+                    .filter_map(|frame| match frame.span {
+                        Span::Code(span) => Some((frame.function, span)),
+                        Span::Builtin => None,
+                    })
+                    .enumerate()
+                    .map(|(id, (function, span))| {
+                        let mut json = json!({ "id": id, "name": function.to_string() });
+                        self.set_location(&mut json, &span);
+                        json
+                    })
+                    .collect();
+                let total = frames.len();
+                out.respond(
+                    request,
+                    json!({ "stackFrames": frames, "totalFrames": total }),
+                );
+            }
+            "scopes" => {
+                let mut scopes = vec![json!({
+                    "name": "Stack",
+                    "variablesReference": STACK_REF,
+                    "expensive": false,
+                })];
+                for stack in enum_iterator::all::<TempStack>() {
+                    scopes.push(json!({
+                        "name": format!("{} stack", capitalize(&stack.to_string())),
+                        "variablesReference": STACK_REF + 1 + stack as i64,
+                        "expensive": false,
+                    }));
+                }
+                out.respond(request, json!({ "scopes": scopes }));
+            }
+            "variables" => {
+                let reference = args["variablesReference"].as_i64().unwrap_or(0);
+                let values = state.map_or(&[][..], |state| {
+                    if reference == STACK_REF {
+                        state.stack()
+                    } else {
+                        enum_iterator::all::<TempStack>()
+                            .find(|&stack| reference == STACK_REF + 1 + stack as i64)
+                            .map_or(&[][..], |stack| state.temp_stack(stack))
+                    }
+                });
+                let variables: Vec<Json> = (values.iter().rev())
+                    .enumerate()
+                    .map(|(i, value)| {
+                        json!({
+                            "name": i.to_string(),
+                            "value": value.show(),
+                            "type": value.type_name(),
+                            "variablesReference": 0,
+                        })
+                    })
+                    .collect();
+                out.respond(request, json!({ "variables": variables }));
+            }
+            command @ ("continue" | "next" | "stepIn" | "stepOut") => {
+                match (command, state) {
+                    ("next", Some(state)) => self.stepper.step_over(state),
+                    ("stepIn", _) => self.stepper.step_in(),
+                    ("stepOut", Some(state)) => self.stepper.step_out(state),
+                    _ => self.stepper.resume(),
+                }
+                let body = if command == "continue" {
+                    json!({ "allThreadsContinued": true })
+                } else {
+                    json!({})
+                };
+                out.respond(request, body);
+                return Control::Resume;
+            }
+            "pause" => {
+                self.stepper.pause();
+                out.respond(request, json!({}));
+            }
+            "terminate" => {
+                self.terminated.store(true, Ordering::Relaxed);
+                out.respond(request, json!({}));
+                return Control::Abort;
+            }
+            "disconnect" => {
+                self.terminated.store(true, Ordering::Relaxed);
+                self.disconnected.store(true, Ordering::Relaxed);
+                out.respond(request, json!({}));
+                return Control::Abort;
+            }
+            command => out.respond_error(request, format!("Unsupported request `{command}`")),
+        }
+        Control::Wait
+    }
+    fn set_location(&self, json: &mut Json, span: &CodeSpan) {
+        let line_offset = if self.lines_start_at_1 { 0 } else { 1 };
+        let column_offset = if self.columns_start_at_1 { 0 } else { 1 };
+        json["line"] = (span.start.line - line_offset).into();
+        json["column"] = (span.start.col - column_offset).into();
+        json["endLine"] = (span.end.line - line_offset).into();
+        json["endColumn"] = (span.end.col - column_offset).into();
+        if let Some(path) = &span.path {
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+            json["source"] = json!({
+                "name": path.file_name().map(|name| name.to_string_lossy()),
+                "path": path,
+            });
+        }
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    (chars.next().into_iter())
+        .flat_map(char::to_uppercase)
+        .chain(chars)
+        .collect()
+}
+
+/// A backend that sends standard output to the client
+///
+/// Stdin is reserved for the protocol, so the program always sees it as empty.
+struct DapSys {
+    out: Output,
+}
+
+impl SysBackend for DapSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.out.output("stdout", s);
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.out.output("stderr", s);
+        Ok(())
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.out.output("console", s);
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        NativeSys.var(name)
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        NativeSys.file_exists(path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        NativeSys.list_dir(path)
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        NativeSys.is_file(path)
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        NativeSys.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        NativeSys.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        if handle == Handle::STDIN {
+            return Ok(Vec::new());
+        }
+        NativeSys.read(handle, count)
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        if handle == Handle::STDIN {
+            return Ok(Vec::new());
+        }
+        NativeSys.read_until(handle, delim)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        NativeSys.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        NativeSys.create_file(path)
+    }
+    fn open_file(&self, path: &Path) -> Result<Handle, String> {
+        NativeSys.open_file(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        NativeSys.file_read_all(path)
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        NativeSys.file_write_all(path, contents)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        NativeSys.sleep(seconds)
+    }
+    fn show_image(&self, image: DynamicImage) -> Result<(), String> {
+        NativeSys.show_image(image)
+    }
+    fn show_gif(&self, gif_bytes: Vec<u8>) -> Result<(), String> {
+        NativeSys.show_gif(gif_bytes)
+    }
+    fn play_audio(&self, wave_bytes: Vec<u8>) -> Result<(), String> {
+        NativeSys.play_audio(wave_bytes)
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        NativeSys.audio_sample_rate()
+    }
+    fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+        NativeSys.stream_audio(f)
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        NativeSys.tcp_listen(addr)
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        NativeSys.tcp_accept(handle)
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        NativeSys.tcp_connect(addr)
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<String, String> {
+        NativeSys.tcp_addr(handle)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        NativeSys.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        NativeSys.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        NativeSys.tcp_set_write_timeout(handle, timeout)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        NativeSys.close(handle)
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        NativeSys.invoke(path)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        NativeSys.run_command_capture(command, args)
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        let (status, out, err) = NativeSys.run_command_capture(command, args)?;
+        self.out.output("stdout", out);
+        self.out.output("stderr", err);
+        Ok(status)
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        NativeSys.change_directory(path)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        NativeSys.https_get(request, handle)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cmp::min,
+        io::{Read, Write},
+    };
+
+    use crossbeam_channel::{Receiver, Sender};
+
+    use super::*;
+
+    /// One end of an in-memory byte stream
+    struct PipeWriter(Sender<Vec<u8>>);
+
+    impl Write for PipeWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            (self.0.send(buf.to_vec())).map_err(|_| io::ErrorKind::BrokenPipe)?;
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The other end of an in-memory byte stream
+    struct PipeReader {
+        recv: Receiver<Vec<u8>>,
+        buf: Vec<u8>,
+        pos: usize,
+    }
+
+    impl Read for PipeReader {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            if self.pos == self.buf.len() {
+                match self.recv.recv_timeout(Duration::from_secs(10)) {
+                    Ok(buf) => {
+                        self.buf = buf;
+                        self.pos = 0;
+                    }
+                    Err(e) if e.is_timeout() => return Err(io::ErrorKind::TimedOut.into()),
+                    Err(_) => return Ok(0),
+                }
+            }
+            let n = min(out.len(), self.buf.len() - self.pos);
+            out[..n].copy_from_slice(&self.buf[self.pos..][..n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    fn pipe() -> (PipeWriter, BufReader<PipeReader>) {
+        let (send, recv) = crossbeam_channel::unbounded();
+        let reader = PipeReader {
+            recv,
+            buf: Vec::new(),
+            pos: 0,
+        };
+        (PipeWriter(send), BufReader::new(reader))
+    }
+
+    struct Client {
+        writer: PipeWriter,
+        reader: BufReader<PipeReader>,
+        seq: i64,
+        events: Vec<Json>,
+    }
+
+    impl Client {
+        fn next_message(&mut self) -> Json {
+            read_message(&mut self.reader)
+                .expect("Failed to read message")
+                .expect("The adapter closed the connection")
+        }
+        fn request(&mut self, command: &str, arguments: Json) -> Json {
+            self.seq += 1;
+            let content = json!({
+                "seq": self.seq,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            })
+            .to_string();
+            write!(
+                self.writer,
+                "Content-Length: {}\r\n\r\n{content}",
+                content.len()
+            )
+            .unwrap();
+            loop {
+                let message = self.next_message();
+                if message["type"] == "response" && message["request_seq"] == self.seq {
+                    assert_eq!(message["command"], command);
+                    return message;
+                }
+                self.events.push(message);
+            }
+        }
+        fn event(&mut self, event: &str) -> Json {
+            if let Some(i) = self.events.iter().position(|m| m["event"] == event) {
+                return self.events.remove(i);
+            }
+            loop {
+                let message = self.next_message();
+                if message["event"] == event {
+                    return message;
+                }
+                self.events.push(message);
+            }
+        }
+    }
+
+    #[test]
+    fn launch_break_continue() {
+        let program = std::env::temp_dir().join(format!("uiua-dap-{}.ua", std::process::id()));
+        fs::write(&program, "F ← +1\n×2 F 3\n&p \"hi\"\n").unwrap();
+        let (client_writer, server_reader) = pipe();
+        let (server_writer, client_reader) = pipe();
+        let server = thread::spawn(move || serve(server_reader, server_writer));
+        let mut client = Client {
+            writer: client_writer,
+            reader: client_reader,
+            seq: 0,
+            events: Vec::new(),
+        };
+
+        let response = client.request("initialize", json!({ "adapterID": "uiua" }));
+        assert_eq!(response["success"], true);
+        client.event("initialized");
+        let response = client.request("launch", json!({ "program": program }));
+        assert_eq!(response["success"], true);
+        let response = client.request(
+            "setBreakpoints",
+            json!({ "source": { "path": program }, "breakpoints": [{ "line": 2 }] }),
+        );
+        assert_eq!(response["body"]["breakpoints"][0]["verified"], true);
+        client.request("configurationDone", json!({}));
+
+        // Stop at the breakpoint, then step to the next line
+        let stopped = client.event("stopped");
+        assert_eq!(stopped["body"]["reason"], "breakpoint");
+        let response = client.request("stackTrace", json!({ "threadId": THREAD_ID }));
+        assert_eq!(response["body"]["stackFrames"][0]["line"], 2);
+        client.request("next", json!({ "threadId": THREAD_ID }));
+        let stopped = client.event("stopped");
+        assert_eq!(stopped["body"]["reason"], "step");
+        let response = client.request("stackTrace", json!({ "threadId": THREAD_ID }));
+        assert_eq!(response["body"]["stackFrames"][0]["line"], 3);
+        let response = client.request("variables", json!({ "variablesReference": STACK_REF }));
+        assert_eq!(response["body"]["variables"][1]["value"], "8");
+
+        // Run to the end
+        let response = client.request("continue", json!({ "threadId": THREAD_ID }));
+        assert_eq!(response["success"], true);
+        let exited = client.event("exited");
+        assert_eq!(exited["body"]["exitCode"], 0);
+        client.event("terminated");
+        let output: String = (client.events.iter())
+            .filter(|m| m["event"] == "output" && m["body"]["category"] == "stdout")
+            .filter_map(|m| m["body"]["output"].as_str())
+            .collect();
+        assert_eq!(output, "hi\n8\n");
+
+        // Requests about the program fail once it has exited
+        let response = client.request("stackTrace", json!({ "threadId": THREAD_ID }));
+        assert_eq!(response["success"], false);
+        assert_eq!(response["message"], "The program has exited");
+        let response = client.request("disconnect", json!({}));
+        assert_eq!(response["success"], true);
+        server.join().unwrap();
+        _ = fs::remove_file(&program);
+    }
+}
@@ -0,0 +1,301 @@
+//! Hooks for stepping through a running program
+//!
+//! A [`Debugger`] is set with [`Uiua::with_debugger`] and is called before
+//! every instruction. A [`Stepper`] implements the usual pause, step, and
+//! breakpoint logic on top of that, so a debugger only has to decide what to do
+//! when the program is paused.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{CodeSpan, Function, FunctionId, Instr, Span, TempStack, Uiua, Value};
+
+/// A hook that is called before each instruction is executed
+///
+/// Blocking in [`Debugger::before_instr`] pauses the program.
+///
+/// Threads spawned by the program are not debugged.
+pub trait Debugger: Send + 'static {
+    /// Called before an instruction is executed
+    fn before_instr(&mut self, state: &DebugState) -> DebugAction;
+}
+
+/// What the interpreter should do after a [`Debugger`] hook returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    /// Execute the instruction
+    Continue,
+    /// Stop the program with an error
+    Abort,
+}
+
+/// A view of the interpreter's state before an instruction is executed
+pub struct DebugState<'a> {
+    pub(crate) env: &'a Uiua,
+    pub(crate) instr: &'a Instr,
+}
+
+/// A frame in the call stack of a paused program
+#[derive(Debug, Clone)]
+pub struct DebugFrame {
+    /// The function being executed
+    pub function: FunctionId,
+    /// The span of the instruction the frame is executing
+    pub span: Span,
+    /// The span at which the function was called
+    pub call_span: Span,
+}
+
+impl<'a> DebugState<'a> {
+    /// Get the instruction about to be executed
+    pub fn instr(&self) -> &'a Instr {
+        self.instr
+    }
+    /// Get the span of the instruction about to be executed
+    ///
+    /// Some instructions, like those pushing literals, do not have a span.
+    pub fn span(&self) -> Option<Span> {
+        instr_span(self.instr).map(|span| self.env.get_span(span))
+    }
+    /// Get the code span of the instruction about to be executed
+    pub fn code_span(&self) -> Option<CodeSpan> {
+        match self.span()? {
+            Span::Code(span) => Some(span),
+            Span::Builtin => None,
+        }
+    }
+    /// Get the depth of the call stack
+    pub fn depth(&self) -> usize {
+        let env = self.env;
+        (env.higher_scopes.iter())
+            .chain([&env.scope])
+            .map(|scope| scope.call.len())
+            .sum()
+    }
+    /// Get the frames of the call stack, innermost first
+    pub fn frames(&self) -> Vec<DebugFrame> {
+        let env = self.env;
+        let mut frames = Vec::new();
+        for scope in env.higher_scopes.iter().chain([&env.scope]) {
+            for frame in &scope.call {
+                let span = (frame.function.instrs.get(frame.pc))
+                    .and_then(instr_span)
+                    .or_else(|| frame.spans.last().map(|(span, _)| *span))
+                    .unwrap_or(frame.call_span);
+                frames.push(DebugFrame {
+                    function: frame.function.id.clone(),
+                    span: env.get_span(span),
+                    call_span: env.get_span(frame.call_span),
+                });
+            }
+        }
+        frames.reverse();
+        frames
+    }
+    /// Get the function being executed
+    pub fn function(&self) -> &'a Arc<Function> {
+        &self.env.scope.call.last().unwrap().function
+    }
+    /// Get the value stack, with the top of the stack last
+    pub fn stack(&self) -> &'a [Value] {
+        &self.env.stack
+    }
+    /// Get the function stack
+    pub fn function_stack(&self) -> &'a [Arc<Function>] {
+        &self.env.function_stack
+    }
+    /// Get one of the temporary stacks
+    pub fn temp_stack(&self, stack: TempStack) -> &'a [Value] {
+        &self.env.temp_stacks[stack as usize]
+    }
+}
+
+fn instr_span(instr: &Instr) -> Option<usize> {
+    Some(match instr {
+        Instr::EndArray { span, .. }
+        | Instr::Prim(_, span)
+        | Instr::ImplPrim(_, span)
+        | Instr::Call(span)
+        | Instr::Switch { span, .. }
+        | Instr::Unpack { span, .. }
+        | Instr::GetTempFunction { span, .. }
+        | Instr::PushTemp { span, .. }
+        | Instr::PopTemp { span, .. }
+        | Instr::CopyToTemp { span, .. }
+        | Instr::CopyFromTemp { span, .. }
+        | Instr::DropTemp { span, .. } => *span,
+        Instr::Push(_)
+        | Instr::BeginArray
+        | Instr::PushFunc(_)
+        | Instr::Dynamic(_)
+        | Instr::PushTempFunctions(_)
+        | Instr::PopTempFunctions(_)
+        | Instr::PushSig(_)
+        | Instr::PopSig => return None,
+    })
+}
+
+/// Why a [`Stepper`] paused the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    /// A breakpoint was hit
+    Breakpoint,
+    /// A step finished
+    Step,
+    /// A pause was requested
+    Pause,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepMode {
+    Run,
+    Pause,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+/// Pause, step, and breakpoint logic for a [`Debugger`]
+///
+/// Steps and breakpoints work on whole lines.
+/// Only instructions with a code span can pause the program.
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use uiua::*;
+///
+/// struct Lines(Stepper, Arc<Mutex<Vec<usize>>>);
+///
+/// impl Debugger for Lines {
+///     fn before_instr(&mut self, state: &DebugState) -> DebugAction {
+///         if self.0.check(state).is_some() {
+///             self.1.lock().unwrap().push(state.code_span().unwrap().start.line);
+///             self.0.step_over(state);
+///         }
+///         DebugAction::Continue
+///     }
+/// }
+///
+/// let lines = Arc::new(Mutex::new(Vec::new()));
+/// let mut stepper = Stepper::new();
+/// stepper.set_breakpoints(None, [2]);
+/// let mut env = Uiua::with_native_sys().with_debugger(Lines(stepper, lines.clone()));
+/// env.load_str("F ← +1\n×2 F 3\n⇌ [1 2 3]").unwrap();
+/// assert_eq!(*lines.lock().unwrap(), [2, 3]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Stepper {
+    mode: StepMode,
+    breakpoints: HashMap<Option<PathBuf>, BTreeSet<usize>>,
+    canonical: HashMap<PathBuf, PathBuf>,
+    /// The last line seen at each call depth
+    lines: Vec<Option<Line>>,
+    /// Where the program last paused
+    paused_at: Option<(Line, usize)>,
+}
+
+type Line = (Option<Arc<Path>>, usize);
+
+impl Default for Stepper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stepper {
+    /// Create a new stepper that runs until it hits a breakpoint
+    pub fn new() -> Self {
+        Self {
+            mode: StepMode::Run,
+            breakpoints: HashMap::new(),
+            canonical: HashMap::new(),
+            lines: Vec::new(),
+            paused_at: None,
+        }
+    }
+    /// Set the breakpoint lines for a file, replacing any previous ones
+    ///
+    /// A `None` path sets breakpoints for code that was not loaded from a file.
+    /// Paths are compared after canonicalization.
+    pub fn set_breakpoints(&mut self, path: Option<&Path>, lines: impl IntoIterator<Item = usize>) {
+        let path = path.map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.into()));
+        let lines: BTreeSet<usize> = lines.into_iter().collect();
+        if lines.is_empty() {
+            self.breakpoints.remove(&path);
+        } else {
+            self.breakpoints.insert(path, lines);
+        }
+    }
+    /// Remove all breakpoints
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+    /// Pause at the next instruction with a code span
+    pub fn pause(&mut self) {
+        self.mode = StepMode::Pause;
+    }
+    /// Run until a breakpoint is hit
+    pub fn resume(&mut self) {
+        self.mode = StepMode::Run;
+    }
+    /// Pause at the next line, including lines in called functions
+    pub fn step_in(&mut self) {
+        self.mode = StepMode::StepIn;
+    }
+    /// Pause at the next line, skipping over called functions
+    pub fn step_over(&mut self, state: &DebugState) {
+        self.mode = StepMode::StepOver(state.depth());
+    }
+    /// Pause once the current function returns
+    pub fn step_out(&mut self, state: &DebugState) {
+        self.mode = StepMode::StepOut(state.depth());
+    }
+    /// Check whether the program should pause before the current instruction
+    ///
+    /// Once this returns a reason, the stepper runs until one of the stepping
+    /// methods is called again.
+    pub fn check(&mut self, state: &DebugState) -> Option<PauseReason> {
+        if self.mode == StepMode::Run && self.breakpoints.is_empty() {
+            // Nothing can pause the program, so there is no need to track lines
+            self.lines.clear();
+            return None;
+        }
+        let span = state.code_span()?;
+        let depth = state.depth();
+        let line: Line = (span.path.clone(), span.start.line);
+        // Returning to a line from a function it called does not count as moving
+        self.lines.resize(depth + 1, None);
+        let moved = self.lines[depth].as_ref() != Some(&line);
+        self.lines[depth] = Some(line.clone());
+        let location = (line, depth);
+        let mode = self.mode;
+        let reason = match mode {
+            StepMode::Pause => Some(PauseReason::Pause),
+            StepMode::StepIn if self.paused_at.as_ref() != Some(&location) => {
+                Some(PauseReason::Step)
+            }
+            StepMode::StepOver(d) if depth < d || depth == d && moved => Some(PauseReason::Step),
+            StepMode::StepOut(d) if depth < d => Some(PauseReason::Step),
+            _ if moved && self.is_breakpoint(&span) => Some(PauseReason::Breakpoint),
+            _ => None,
+        }?;
+        self.mode = StepMode::Run;
+        self.paused_at = Some(location);
+        Some(reason)
+    }
+    fn is_breakpoint(&mut self, span: &CodeSpan) -> bool {
+        if self.breakpoints.is_empty() {
+            return false;
+        }
+        let path = span.path.as_ref().map(|path| {
+            (self.canonical.entry(path.to_path_buf()))
+                .or_insert_with(|| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+                .clone()
+        });
+        (self.breakpoints.get(&path)).is_some_and(|lines| lines.contains(&span.start.line))
+    }
+}
@@ -1,6 +1,8 @@
 use std::{convert::Infallible, error::Error, fmt, io, path::PathBuf, sync::Arc};
 
 use colored::*;
+#[cfg(feature = "json")]
+use serde_json::{json, Map, Value as Json};
 
 use crate::{
     function::FunctionId,
@@ -31,6 +33,8 @@ pub enum UiuaError {
     Throw(Box<Value>, Span),
     /// Maximum execution time exceeded
     Timeout(Span),
+    /// The program was stopped with an [`InterruptHandle`](crate::InterruptHandle)
+    Interrupted(Span),
     /// A wrapper marking this error as being fill-related
     Fill(Box<Self>),
 }
@@ -84,6 +88,7 @@ impl fmt::Display for UiuaError {
             }
             UiuaError::Throw(value, span) => write!(f, "{span}: {value}"),
             UiuaError::Timeout(_) => write!(f, "Maximum execution time exceeded"),
+            UiuaError::Interrupted(_) => write!(f, "Program was interrupted"),
             UiuaError::Fill(error) => error.fmt(f),
         }
     }
@@ -105,6 +110,14 @@ impl UiuaError {
             error => error.message().into(),
         }
     }
+    /// Check if the error was caused by an [`InterruptHandle`](crate::InterruptHandle)
+    pub fn is_interrupted(&self) -> bool {
+        match self {
+            UiuaError::Traced { error, .. } | UiuaError::Fill(error) => error.is_interrupted(),
+            UiuaError::Interrupted(_) => true,
+            _ => false,
+        }
+    }
     /// Check if the error is fill-related
     pub(crate) fn is_fill(&self) -> bool {
         match self {
@@ -182,6 +195,9 @@ impl UiuaError {
             UiuaError::Timeout(span) => {
                 Report::new_multi(kind, [("Maximum execution time exceeded", span.clone())])
             }
+            UiuaError::Interrupted(span) => {
+                Report::new_multi(kind, [("Program was interrupted", span.clone())])
+            }
             UiuaError::Fill(error) => error.report(),
             UiuaError::Load(..) | UiuaError::Format(..) => Report::new(kind, self.to_string()),
         }
@@ -281,6 +297,10 @@ pub struct Report {
     ///
     /// Defaults to `true`
     pub color: bool,
+    /// The messages of the report and where they come from
+    pub messages: Vec<(String, Span)>,
+    /// The call trace of an error
+    pub trace: Vec<TraceFrame>,
 }
 
 impl Report {
@@ -297,19 +317,23 @@ impl Report {
             self.fragments.push(ReportFragment::Newline);
             self.fragments.push(ReportFragment::Plain(line));
         }
+        self.trace.extend_from_slice(trace);
         self
     }
     /// Create a new report
     pub fn new(kind: ReportKind, message: impl Into<String>) -> Self {
+        let message = message.into();
         let fragments = vec![
             ReportFragment::Colored(kind.str().into()),
             ReportFragment::Plain(": ".into()),
-            ReportFragment::Plain(message.into()),
+            ReportFragment::Plain(message.clone()),
         ];
         Self {
             kind,
             fragments,
             color: true,
+            messages: vec![(message, Span::Builtin)],
+            trace: Vec::new(),
         }
     }
     /// Create a new report with multiple messages
@@ -319,7 +343,9 @@ impl Report {
         T: fmt::Display,
     {
         let mut fragments = Vec::new();
+        let mut messages = Vec::new();
         for (message, span) in errors {
+            messages.push((message.to_string(), span.clone()));
             fragments.push(ReportFragment::Colored(kind.str().into()));
             fragments.push(ReportFragment::Plain(": ".into()));
             for (i, line) in message.to_string().lines().enumerate() {
@@ -373,8 +399,69 @@ impl Report {
             kind,
             fragments,
             color: true,
+            messages,
+            trace: Vec::new(),
+        }
+    }
+    /// Convert the report to JSON, with one object per message on each line
+    ///
+    /// Each object has the report's `kind`, the `message`, and the `file`, `start`, and `end`
+    /// of the message's span, as well as the `trace` of an error.
+    /// Lines and columns start at 1.
+    ///
+    /// ```
+    /// use uiua::*;
+    ///
+    /// let error = Uiua::with_native_sys().load_str("+@a @b").unwrap_err();
+    /// let json = error.report().to_json();
+    /// assert!(json.starts_with(r#"{"kind":"error","message":"Cannot add character and character","#));
+    /// assert!(json.contains(r#""start":{"line":1,"column":1}"#));
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        let trace: Vec<Json> = (self.trace.iter())
+            .filter(|frame| frame.id != FunctionId::Main)
+            .map(|frame| {
+                let function = match &frame.id {
+                    FunctionId::Named(name) => name.to_string(),
+                    id => id.to_string(),
+                };
+                let mut json = Map::new();
+                json.insert("function".into(), function.into());
+                json.extend(span_json(&frame.span));
+                json.into()
+            })
+            .collect();
+        let kind = self.kind.str().to_lowercase();
+        let mut lines = Vec::new();
+        for (message, span) in &self.messages {
+            let mut json = Map::new();
+            json.insert("kind".into(), kind.clone().into());
+            json.insert("message".into(), message.clone().into());
+            json.extend(span_json(span));
+            json.insert("trace".into(), trace.clone().into());
+            lines.push(Json::Object(json).to_string());
         }
+        lines.join("\n")
+    }
+}
+
+#[cfg(feature = "json")]
+fn span_json(span: &Span) -> Map<String, Json> {
+    let mut json = Map::new();
+    let Span::Code(span) = span else {
+        json.insert("file".into(), Json::Null);
+        json.insert("start".into(), Json::Null);
+        json.insert("end".into(), Json::Null);
+        return json;
+    };
+    let file = (span.path.as_ref()).map(|path| path.display().to_string());
+    json.insert("file".into(), file.into());
+    for (name, loc) in [("start", span.start), ("end", span.end)] {
+        let loc = json!({ "line": loc.line, "column": loc.col });
+        json.insert(name.into(), loc);
     }
+    json
 }
 
 impl fmt::Display for Report {
@@ -21,10 +21,10 @@ use crate::{
     Ident, Primitive, SysBackend, SysOp, Uiua, UiuaError, UiuaResult,
 };
 
-// For now disallow any syscalls in the format config file.
-struct FormatConfigBackend;
+// For now disallow any syscalls in config files.
+pub(crate) struct ConfigBackend;
 
-impl SysBackend for FormatConfigBackend {
+impl SysBackend for ConfigBackend {
     fn any(&self) -> &dyn Any {
         self
     }
@@ -33,7 +33,7 @@ impl SysBackend for FormatConfigBackend {
     }
 }
 
-trait ConfigValue: Sized {
+pub(crate) trait ConfigValue: Sized {
     fn from_value(value: &Value, env: &Uiua, requirement: &'static str) -> UiuaResult<Self>;
 }
 
@@ -116,7 +116,7 @@ macro_rules! create_config {
         impl PartialFormatConfig {
             paste! {
                 fn from_file(file_path: PathBuf) -> UiuaResult<Self> {
-                    let mut env = Uiua::with_backend(FormatConfigBackend)
+                    let mut env = Uiua::with_backend(ConfigBackend)
                         .print_diagnostics(true);
                     env.load_file(file_path)?;
                     let mut bindings = env.all_values_is_scope();
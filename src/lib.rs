@@ -12,7 +12,7 @@ The `uiua` crate is set up primarily to be installed as a binary. For this reaso
 uiua = { version = "*", default-features = false }
 ```
 
-The main entry point is the [`Uiua`] struct, which is the Uiua runtime. It must be created with a [`SysBackend`]. [`Uiua::with_native_sys`] is a convenient way to create a Uiua runtime that uses the same backend as the Uiua CLI, though keep in mind it gives full access to the filesystem and TCP sockets and so probably shouldn't be used in a sandboxed environment.
+The main entry point is the [`Uiua`] struct, which is the Uiua runtime. It must be created with a [`SysBackend`]. [`Uiua::with_native_sys`] is a convenient way to create a Uiua runtime that uses the same backend as the Uiua CLI, though keep in mind it gives full access to the filesystem and TCP sockets and so probably shouldn't be used in a sandboxed environment. [`MemorySys`] is a backend with an in-memory filesystem that can be used instead, and any backend can be restricted with a [`PolicySys`].
 
 [`Value`] is the generic value type. It wraps one of four [`Array`] types.
 
@@ -66,6 +66,9 @@ assert_eq!(formatted, "↯3_4⇡12");
 The `uiua` crate has the following feature flags:
 - `bytes`: Enables a byte array type. This type is semantically equivalent to a numeric array, but takes up less space. It is returned by certain file and network functions, as well as some comparison functions.
 - `audio`: Enables audio features in the [`NativeSys`] backend.
+- `serde`: Implements `Serialize` and `Deserialize` for [`Value`], [`Array`], [`Boxed`], and [`Complex`].
+- `dap`: Enables a Debug Adapter Protocol server built on [`Debugger`].
+- `replay`: Enables the `RecordSys` and `ReplaySys` backends, which record and replay the nondeterministic system calls made by a program.
 */
 
 #![allow(clippy::single_match, clippy::needless_range_loop)]
@@ -79,22 +82,33 @@ mod check;
 mod compile;
 mod complex;
 mod cowslice;
+#[cfg(feature = "dap")]
+mod dap;
+mod debug;
 mod error;
 pub mod format;
 mod function;
 mod grid_fmt;
 mod lex;
+pub mod lint;
 mod lsp;
+mod npy;
 mod parse;
 mod primitive;
 #[doc(hidden)]
 pub mod profile;
 mod run;
+#[cfg(feature = "serde")]
+mod serialize;
 #[cfg(feature = "stand")]
 #[doc(hidden)]
 pub mod stand;
 mod sys;
 mod sys_native;
+mod sys_policy;
+#[cfg(feature = "replay")]
+mod sys_replay;
+mod test_runner;
 mod value;
 
 use std::sync::Arc;
@@ -102,21 +116,29 @@ use std::sync::Arc;
 pub use self::{
     array::*,
     boxed::*,
+    debug::*,
     error::*,
     function::*,
     lex::is_ident_char,
     lex::*,
     lsp::*,
     lsp::{spans, SpanKind},
+    npy::{npz_bytes_to_values, values_to_npz_bytes},
     parse::{parse, ParseError},
     primitive::*,
     run::*,
     sys::*,
     sys_native::*,
+    sys_policy::*,
+    test_runner::*,
     value::*,
 };
 
 pub use complex::*;
+#[cfg(feature = "dap")]
+pub use dap::run_debug_adapter;
+#[cfg(feature = "replay")]
+pub use sys_replay::*;
 
 /// The Uiua version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -129,7 +151,11 @@ fn suite() {
     for entry in std::fs::read_dir("tests").unwrap() {
         let entry = entry.unwrap();
         let path = entry.path();
-        if path.is_file() && path.extension().is_some_and(|s| s == "ua") {
+        // `files.ua` uses the filesystem, so it is run with `MemorySys` in `tests/files.rs`
+        if path.is_file()
+            && path.extension().is_some_and(|s| s == "ua")
+            && path.file_name().is_some_and(|name| name != "files.ua")
+        {
             let mut env = Uiua::with_native_sys();
             if let Err(e) = env.load_file(&path) {
                 panic!("Test failed in {}:\n{}", path.display(), e.report());
@@ -0,0 +1,599 @@
+//! The Uiua linter
+//!
+//! Lints are reported as diagnostics by [`check_code`](crate::check_code) and
+//! [`check_code_static`](crate::check_code_static). Each rule can be turned off in a
+//! `.lint.ua` file, which is found the same way as the formatter's `.fmt.ua`.
+//!
+//! ```
+//! use uiua::*;
+//!
+//! let check = check_code_static("F ← ;1 ∘ +\nF ← ⊙⊙⊙⊙+\nF 1 2 3 4 5 6", None);
+//! let diagnostics: Vec<String> = (check.diagnostics.iter())
+//!     .map(|diagnostic| diagnostic.message.clone())
+//!     .collect();
+//! assert_eq!(diagnostics, [
+//!     "Binding `F` is never used",
+//!     "; pops a value that was just pushed",
+//!     "∘ does nothing here",
+//!     "Binding `F` shadows an earlier binding with the same name",
+//!     "4 nested dips are hard to follow. \
+//!     Consider rearranging the stack or splitting the function.",
+//! ]);
+//! ```
+
+#[cfg(feature = "lsp")]
+use std::{collections::HashMap, time::SystemTime};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    slice,
+};
+
+use paste::paste;
+
+use crate::{
+    algorithm::invert::invert_instrs,
+    ast::*,
+    format::{ConfigBackend, ConfigValue},
+    function::Instr,
+    lex::{CodeSpan, Sp},
+    primitive::PrimClass,
+    Diagnostic, DiagnosticKind, Ident, Primitive, Uiua, UiuaResult,
+};
+
+macro_rules! expects {
+    (bool) => {
+        "a boolean"
+    };
+    (usize) => {
+        "a natural number"
+    };
+}
+
+macro_rules! create_config {
+    ($(
+        $(#[doc = $doc:literal])*
+        ($name:ident, $ty:ident, $default:expr)
+    ),* $(,)?) => {
+        /// Which lints are enabled
+        ///
+        /// Each option can be set in a `.lint.ua` file by binding its name in PascalCase,
+        /// e.g. `UnusedBinding ← 0`.
+        #[derive(Debug, Clone)]
+        pub struct LintConfig {
+            $(
+                $(#[doc = $doc])*
+                pub $name: $ty,
+            )*
+        }
+
+        impl Default for LintConfig {
+            fn default() -> Self {
+                Self {
+                    $(
+                        $name: $default,
+                    )*
+                }
+            }
+        }
+
+        impl LintConfig {
+            paste! {
+                /// Load the lint configuration from the specified file
+                ///
+                /// Options that are not bound keep their default values.
+                pub fn from_file(path: PathBuf) -> UiuaResult<Self> {
+                    let mut env = Uiua::with_backend(ConfigBackend);
+                    env.load_file(path)?;
+                    let mut bindings = env.all_values_is_scope();
+                    let mut config = Self::default();
+                    $(
+                        if let Some(value) = bindings.remove(stringify!([<$name:camel>])) {
+                            let requirement = concat!(
+                                "Lint config option '",
+                                stringify!([<$name:camel>]),
+                                "' expects ",
+                                expects!($ty)
+                            );
+                            config.$name = $ty::from_value(&value, &env, requirement)?;
+                        }
+                    )*
+                    Ok(config)
+                }
+            }
+        }
+    };
+}
+
+create_config!(
+    /// Whether to report bindings that are never used
+    ///
+    /// Top-level bindings are only reported if they are shadowed before being used,
+    /// since other files may import them.
+    (unused_binding, bool, true),
+    /// Whether to report bindings that shadow an earlier binding with the same name
+    (shadowed_name, bool, true),
+    /// Whether to report bindings with no declared signature whose inferred signature depends
+    /// on a repetition count or on which switch branch is taken
+    (ambiguous_signature, bool, true),
+    /// Whether to report the primitives that make an `invert`ed function non-invertible
+    (invert_non_invertible, bool, true),
+    /// Whether to report deeply nested `dip`s
+    (deep_dip, bool, true),
+    /// The deepest nesting of `dip`s that is not reported
+    (max_dip_depth, usize, 3),
+    /// Whether to report `identity`s that do nothing
+    (redundant_identity, bool, true),
+    /// Whether to report `pop`s of values that were just pushed
+    (pop_after_push, bool, true),
+);
+
+impl LintConfig {
+    /// Find the lint configuration for a file
+    ///
+    /// The nearest `.lint.ua` file in the file's directory or one of its parents is used.
+    /// If there is none, the default configuration is used.
+    pub fn find(path: Option<&Path>) -> UiuaResult<Self> {
+        match Self::search_config_file(path) {
+            Some(file_path) => Self::from_file(file_path),
+            None => Ok(Self::default()),
+        }
+    }
+    fn search_config_file(path: Option<&Path>) -> Option<PathBuf> {
+        let mut path = path
+            .and_then(|p| fs::canonicalize(p).ok())
+            .unwrap_or(env::current_dir().ok()?);
+        loop {
+            let file_path = path.join(".lint.ua");
+            if file_path.exists() {
+                return Some(file_path);
+            }
+            if !path.pop() {
+                return None;
+            }
+        }
+    }
+}
+
+/// A cache of lint configurations for the language server
+///
+/// Each directory is only searched once for a `.lint.ua` file, and a `.lint.ua` file is only
+/// loaded again when its modification time changes. The searches must be cleared with
+/// [`LintConfigCache::clear_searches`] when a `.lint.ua` file is created or deleted.
+#[cfg(feature = "lsp")]
+#[derive(Default)]
+pub(crate) struct LintConfigCache {
+    searches: HashMap<PathBuf, Option<PathBuf>>,
+    configs: HashMap<PathBuf, (Option<SystemTime>, UiuaResult<LintConfig>)>,
+}
+
+#[cfg(feature = "lsp")]
+impl LintConfigCache {
+    /// Get the lint configuration for a file
+    pub fn get(&mut self, path: Option<&Path>) -> UiuaResult<LintConfig> {
+        let dir = match path.and_then(Path::parent) {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => env::current_dir().unwrap_or_default(),
+        };
+        let file_path = (self.searches.entry(dir.clone()))
+            .or_insert_with(|| LintConfig::search_config_file(Some(&dir)))
+            .clone();
+        let Some(file_path) = file_path else {
+            return Ok(LintConfig::default());
+        };
+        let Ok(modified) = fs::metadata(&file_path).map(|meta| meta.modified().ok()) else {
+            // The file was deleted
+            self.clear_searches();
+            return LintConfig::find(Some(&dir));
+        };
+        match self.configs.get(&file_path) {
+            Some((cached, config)) if *cached == modified => config.clone(),
+            _ => {
+                let config = LintConfig::from_file(file_path.clone());
+                self.configs.insert(file_path, (modified, config.clone()));
+                config
+            }
+        }
+    }
+    /// Forget which directories have `.lint.ua` files
+    pub fn clear_searches(&mut self) {
+        self.searches.clear();
+    }
+}
+
+/// Lint some items
+///
+/// `env` should have the items' bindings bound so that signatures can be inferred.
+pub(crate) fn lint(items: &[Item], env: &mut Uiua, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        env,
+        config,
+        diagnostics: Vec::new(),
+        deep_dips: Vec::new(),
+    };
+    if config.unused_binding {
+        for name in unused_bindings(items) {
+            linter.diagnostics.push(Diagnostic::new(
+                format!("Binding `{}` is never used", name.value),
+                name.span,
+                DiagnosticKind::Style,
+            ));
+        }
+    }
+    linter.items(items, &mut Vec::new());
+    linter.diagnostics
+}
+
+struct Linter<'a> {
+    env: &'a mut Uiua,
+    config: &'a LintConfig,
+    diagnostics: Vec<Diagnostic>,
+    /// The spans of reported `dip` chains, so that their inner chains are not reported
+    deep_dips: Vec<CodeSpan>,
+}
+
+impl<'a> Linter<'a> {
+    fn items(&mut self, items: &[Item], bound: &mut Vec<Ident>) {
+        for item in items {
+            match item {
+                Item::Words(words) => self.lines(words),
+                Item::Binding(binding) => {
+                    self.lines(&binding.words);
+                    if self.config.ambiguous_signature && binding.signature.is_none() {
+                        self.ambiguous_signature(binding);
+                    }
+                    if self.config.shadowed_name && bound.contains(&binding.name.value) {
+                        self.diagnostics.push(Diagnostic::new(
+                            format!(
+                                "Binding `{}` shadows an earlier binding with the same name",
+                                binding.name.value
+                            ),
+                            binding.name.span.clone(),
+                            DiagnosticKind::Warning,
+                        ));
+                    }
+                    bound.push(binding.name.value.clone());
+                }
+                Item::TestScope(items) => {
+                    let outer = bound.len();
+                    self.items(&items.value, bound);
+                    bound.truncate(outer);
+                }
+                Item::ExtraNewlines(_) => {}
+            }
+        }
+    }
+    fn lines(&mut self, words: &[Sp<Word>]) {
+        for_each_line(words, &mut |line| self.line(line));
+    }
+    fn line(&mut self, words: &[Sp<Word>]) {
+        let code: Vec<&Sp<Word>> = words.iter().filter(|word| word.value.is_code()).collect();
+        for (i, word) in code.iter().enumerate() {
+            match &word.value {
+                Word::Primitive(Primitive::Identity)
+                    if self.config.redundant_identity && code.len() > 1 =>
+                {
+                    self.diagnostics.push(Diagnostic::new(
+                        format!("{} does nothing here", Primitive::Identity),
+                        word.span.clone(),
+                        DiagnosticKind::Style,
+                    ));
+                }
+                Word::Primitive(Primitive::Pop) if self.config.pop_after_push => {
+                    if let Some(next) = code.get(i + 1).filter(|next| is_literal(&next.value)) {
+                        self.diagnostics.push(Diagnostic::new(
+                            format!("{} pops a value that was just pushed", Primitive::Pop),
+                            word.span.clone().merge(next.span.clone()),
+                            DiagnosticKind::Style,
+                        ));
+                    }
+                }
+                Word::Modified(m) => match m.modifier.value {
+                    Modifier::Primitive(Primitive::Dip) if self.config.deep_dip => {
+                        self.deep_dip(m, &word.span)
+                    }
+                    Modifier::Primitive(Primitive::Invert) if self.config.invert_non_invertible => {
+                        self.invert(m)
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+    fn deep_dip(&mut self, m: &Modified, span: &CodeSpan) {
+        if (self.deep_dips.iter()).any(|outer| {
+            outer.start.char_pos <= span.start.char_pos && span.end.char_pos <= outer.end.char_pos
+        }) {
+            return;
+        }
+        let mut depth = 1;
+        let mut inner = m;
+        while let Some(Word::Modified(m)) = inner.code_operands().next().map(|word| &word.value) {
+            if m.modifier.value != Modifier::Primitive(Primitive::Dip) {
+                break;
+            }
+            depth += 1;
+            inner = m;
+        }
+        if depth > self.config.max_dip_depth {
+            self.diagnostics.push(Diagnostic::new(
+                format!(
+                    "{depth} nested {}s are hard to follow. \
+                    Consider rearranging the stack or splitting the function.",
+                    Primitive::Dip.name()
+                ),
+                span.clone(),
+                DiagnosticKind::Style,
+            ));
+            self.deep_dips.push(span.clone());
+        }
+    }
+    fn invert(&mut self, m: &Modified) {
+        let mut reported = Vec::new();
+        for_each_line(&m.operands, &mut |line| {
+            for word in line {
+                let Word::Primitive(prim) = word.value else {
+                    continue;
+                };
+                if !reported.contains(&prim) && !is_invertible(prim) {
+                    reported.push(prim);
+                    self.diagnostics.push(Diagnostic::new(
+                        format!(
+                            "{prim} has no inverse, so this function cannot be {}ed",
+                            Primitive::Invert.name()
+                        ),
+                        word.span.clone(),
+                        DiagnosticKind::Warning,
+                    ));
+                }
+            }
+        });
+    }
+    fn ambiguous_signature(&mut self, binding: &Binding) {
+        let mut reasons = Vec::new();
+        for_each_line(&binding.words, &mut |line| {
+            for word in line {
+                match &word.value {
+                    Word::Switch(sw) => {
+                        let sigs: Vec<_> = (sw.branches.iter())
+                            .map(|branch| {
+                                let word = branch.clone().map(Word::Func);
+                                self.env.word_function_signature(word)
+                            })
+                            .collect();
+                        if sigs.iter().all(Option::is_some) && sigs.windows(2).any(|w| w[0] != w[1])
+                        {
+                            reasons.push("which switch branch is taken".to_string());
+                        }
+                    }
+                    Word::Modified(m)
+                        if m.modifier.value == Modifier::Primitive(Primitive::Repeat) =>
+                    {
+                        let Some(f) = m.code_operands().next() else {
+                            continue;
+                        };
+                        if let Some(sig) = self.env.word_function_signature(f.clone()) {
+                            if sig.args != sig.outputs {
+                                reasons.push(format!(
+                                    "the number of {} repetitions",
+                                    Primitive::Repeat
+                                ));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+        if let Some(reason) = reasons.first() {
+            self.diagnostics.push(Diagnostic::new(
+                format!(
+                    "The signature of `{}` depends on {reason}. \
+                    A signature can be declared after the `←`.",
+                    binding.name.value
+                ),
+                binding.name.span.clone(),
+                DiagnosticKind::Warning,
+            ));
+        }
+    }
+}
+
+/// Call a function on a line of words and on every line nested in it
+///
+/// Each modifier operand is its own line.
+fn for_each_line(words: &[Sp<Word>], f: &mut impl FnMut(&[Sp<Word>])) {
+    f(words);
+    for word in words {
+        match &word.value {
+            Word::Strand(items) => for_each_line(items, f),
+            Word::Array(arr) => {
+                for line in &arr.lines {
+                    for_each_line(line, f);
+                }
+            }
+            Word::Func(func) => {
+                for line in &func.lines {
+                    for_each_line(line, f);
+                }
+            }
+            Word::Switch(sw) => {
+                for branch in &sw.branches {
+                    for line in &branch.value.lines {
+                        for_each_line(line, f);
+                    }
+                }
+            }
+            Word::Modified(m) => {
+                for operand in &m.operands {
+                    for_each_line(slice::from_ref(operand), f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a word only pushes a constant value
+fn is_literal(word: &Word) -> bool {
+    match word {
+        Word::Number(..) | Word::Char(_) | Word::String(_) | Word::MultilineString(_) => true,
+        Word::Strand(items) => items.iter().all(|item| is_literal(&item.value)),
+        _ => false,
+    }
+}
+
+/// Whether a function primitive can be inverted, either alone or with a constant argument
+fn is_invertible(prim: Primitive) -> bool {
+    if matches!(
+        prim.class(),
+        PrimClass::Stack | PrimClass::Planet | PrimClass::Constant
+    ) || prim.modifier_args().is_some()
+        || prim.args().unwrap_or(0) == 0
+    {
+        return true;
+    }
+    invert_instrs(&[Instr::Prim(prim, 0)]).is_some()
+        || invert_instrs(&[Instr::push(1.0), Instr::Prim(prim, 0)]).is_some()
+}
+
+/// Find the bindings that are never referenced
+///
+/// Top-level bindings may be used by other files that import them,
+/// so they are only unused if they are shadowed before being referenced.
+fn unused_bindings(items: &[Item]) -> Vec<Sp<Ident>> {
+    fn reference(words: &[Sp<Word>], bindings: &mut [(Sp<Ident>, bool)]) {
+        let mark = |bindings: &mut [(Sp<Ident>, bool)], name: &Ident| {
+            if let Some((_, used)) = (bindings.iter_mut().rev()).find(|(b, _)| b.value == *name) {
+                *used = true;
+            }
+        };
+        for_each_line(words, &mut |line| {
+            for word in line {
+                match &word.value {
+                    Word::Ident(ident) => mark(bindings, ident),
+                    Word::Modified(m) => {
+                        if let Modifier::Ident(ident) = &m.modifier.value {
+                            mark(bindings, ident);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+    fn walk(
+        items: &[Item],
+        scope: usize,
+        bindings: &mut Vec<(Sp<Ident>, bool)>,
+        unused: &mut Vec<Sp<Ident>>,
+    ) {
+        for item in items {
+            match item {
+                Item::Words(words) => reference(words, bindings),
+                Item::Binding(binding) => {
+                    reference(&binding.words, bindings);
+                    if let Some(i) = (bindings[scope..].iter())
+                        .position(|(b, used)| !used && b.value == binding.name.value)
+                    {
+                        unused.push(bindings.remove(scope + i).0);
+                    }
+                    bindings.push((binding.name.clone(), false));
+                }
+                Item::TestScope(items) => {
+                    let outer = bindings.len();
+                    walk(&items.value, outer, bindings, unused);
+                    unused.extend(
+                        (bindings.drain(outer..)).filter_map(|(b, used)| (!used).then_some(b)),
+                    );
+                }
+                Item::ExtraNewlines(_) => {}
+            }
+        }
+    }
+    let mut bindings = Vec::new();
+    let mut unused = Vec::new();
+    walk(items, 0, &mut bindings, &mut unused);
+    unused.sort_by_key(|b| b.span.start.char_pos);
+    unused
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lsp::check_code_static_with;
+
+    use super::*;
+
+    fn lints(code: &str, config: LintConfig) -> Vec<String> {
+        let check = check_code_static_with(code, None, Ok(config));
+        (check.diagnostics.into_iter())
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    #[test]
+    fn ambiguous_signature() {
+        let config = LintConfig::default;
+        assert_eq!(
+            lints("F ← (+|∘)", config()),
+            [
+                "The signature of `F` depends on which switch branch is taken. \
+            A signature can be declared after the `←`."
+            ]
+        );
+        assert_eq!(
+            lints("F ← ⍥(.)", config()),
+            [
+                "The signature of `F` depends on the number of ⍥ repetitions. \
+            A signature can be declared after the `←`."
+            ]
+        );
+        assert!(lints("F ← (+|-)", config()).is_empty());
+        assert!(lints("F ← ⍥(+1)", config()).is_empty());
+        let config = LintConfig {
+            ambiguous_signature: false,
+            ..LintConfig::default()
+        };
+        assert!(lints("F ← (+|∘)", config).is_empty());
+    }
+
+    #[test]
+    fn invert_non_invertible() {
+        let config = LintConfig::default;
+        assert_eq!(
+            lints("F ← ⍘(⇌ ⊢)", config()),
+            ["⊢ has no inverse, so this function cannot be inverted"]
+        );
+        assert_eq!(
+            lints("F ← ⍘(⌊ ⊢ ⌊)", config()),
+            [
+                "⌊ has no inverse, so this function cannot be inverted",
+                "⊢ has no inverse, so this function cannot be inverted"
+            ]
+        );
+        assert!(lints("F ← ⍘(⇌ +1 ⍉)", config()).is_empty());
+        let config = LintConfig {
+            invert_non_invertible: false,
+            ..LintConfig::default()
+        };
+        assert!(lints("F ← ⍘(⇌ ⊢)", config).is_empty());
+    }
+
+    #[test]
+    fn unused_binding() {
+        let config = LintConfig::default;
+        assert!(lints("F ← +1\nG ← ×2", config()).is_empty());
+        assert_eq!(
+            lints("F ← +1\nF ← ×2", config()),
+            [
+                "Binding `F` is never used",
+                "Binding `F` shadows an earlier binding with the same name"
+            ]
+        );
+        assert_eq!(
+            lints("F ← +1\n---\nG ← ×2\nH ← -1\nF H 1\n---", config()),
+            ["Binding `G` is never used"]
+        );
+    }
+}
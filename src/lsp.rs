@@ -1,10 +1,11 @@
-use std::slice;
+use std::{any::Any, collections::BTreeSet, fs, path::Path, slice, time::Duration};
 
 use crate::{
     ast::{Item, Modifier, Word},
     lex::{CodeSpan, Loc, Sp},
+    lint::{lint, LintConfig},
     parse::parse,
-    Primitive,
+    Diagnostic, Primitive, Signature, SysBackend, SysOp, Uiua, UiuaError, UiuaResult,
 };
 
 /// Kinds of span in Uiua code, meant to be used in the language server or other IDE tools
@@ -152,24 +153,325 @@ fn words_spans(words: &[Sp<Word>]) -> Vec<Sp<SpanKind>> {
     spans
 }
 
+/// The error message of system functions that are refused while checking
+const CHECK_REFUSAL: &str = "System functions are not run while checking";
+/// How long each top-level item may run while checking
+const CHECK_TIME_LIMIT: Duration = Duration::from_millis(500);
+/// How much memory each array may use while checking
+const CHECK_MEMORY_LIMIT: usize = 256 << 20;
+
+/// What a [`SignatureHint`] is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureHintKind {
+    /// A binding
+    Binding,
+    /// A top-level line
+    Line,
+    /// An inline function
+    Function,
+    /// An operand of a modifier
+    Operand,
+    /// A branch of a switch function
+    Branch,
+}
+
+/// A signature inferred for some code
+#[derive(Debug, Clone)]
+pub struct SignatureHint {
+    /// The span of the code
+    pub span: CodeSpan,
+    /// The inferred signature
+    pub signature: Signature,
+    /// What the code is
+    pub kind: SignatureHintKind,
+}
+
+/// The results of [`check_code`]
+#[derive(Debug, Default)]
+pub struct CodeCheck {
+    /// The errors, each with the span of the top-level item that caused it
+    pub errors: Vec<(UiuaError, Option<CodeSpan>)>,
+    /// The non-error diagnostics
+    pub diagnostics: BTreeSet<Diagnostic>,
+    /// The signatures of bindings, top-level lines, and the functions in them
+    pub signatures: Vec<SignatureHint>,
+}
+
+/// Check code by compiling and running it without running system functions
+///
+/// Each top-level item is checked separately, so checking continues after an error.
+/// Imports are still followed, and bindings that need system functions are compiled
+/// without being run.
+///
+/// The code is then linted with the [`LintConfig`] of the nearest `.lint.ua` file.
+///
+/// ```
+/// use uiua::*;
+///
+/// let check = check_code("F ← +1\nF 2\n&p 3\n⊡ 5 [1 2]", None);
+/// let signatures: Vec<String> = (check.signatures.iter())
+///     .map(|hint| hint.signature.to_string())
+///     .collect();
+/// assert_eq!(signatures, ["|1.1", "|0.1", "|0.0", "|0.1"]);
+/// assert_eq!(check.errors.len(), 1);
+/// ```
+pub fn check_code(input: &str, path: Option<&Path>) -> CodeCheck {
+    check_with(check_env(), input, path, LintConfig::find(path))
+}
+
+/// Check code by compiling it without running it
+///
+/// Unlike [`check_code`], no code is run at all, so only errors that can be found while
+/// compiling are reported. Imports are still followed and compiled the same way.
+///
+/// Uses of experimental primitives are reported as diagnostics, along with lints.
+///
+/// ```
+/// use uiua::*;
+///
+/// let code = "x ← 5\nF ← |2 +1\nG ← +1\nG 2\n⊡ 5 [1 2]\nH\n---\ny ← 1\n---";
+/// let check = check_code_static(code, None);
+/// let errors: Vec<String> = (check.errors.iter())
+///     .map(|(error, _)| error.to_string())
+///     .collect();
+/// assert_eq!(errors.len(), 2);
+/// assert!(errors[0].contains("signature mismatch"));
+/// assert!(errors[1].contains("Unknown identifier `H`"));
+/// let diagnostics: Vec<String> = (check.diagnostics.iter())
+///     .map(|diagnostic| diagnostic.message.clone())
+///     .collect();
+/// assert_eq!(diagnostics, ["Binding `y` is never used"]);
+/// ```
+pub fn check_code_static(input: &str, path: Option<&Path>) -> CodeCheck {
+    check_code_static_with(input, path, LintConfig::find(path))
+}
+
+/// Check code like [`check_code_static`], but with an already loaded lint configuration
+pub(crate) fn check_code_static_with(
+    input: &str,
+    path: Option<&Path>,
+    lint_config: UiuaResult<LintConfig>,
+) -> CodeCheck {
+    let mut env = check_env();
+    env.compile_only = true;
+    check_with(env, input, path, lint_config)
+}
+
+/// Check code one item at a time, then lint it
+fn check_with(
+    mut env: Uiua,
+    input: &str,
+    path: Option<&Path>,
+    lint_config: UiuaResult<LintConfig>,
+) -> CodeCheck {
+    let mut check = CodeCheck::default();
+    let mut items = Vec::new();
+    let res = env.check_str(input, path, |env, item, res| {
+        items.push(item.clone());
+        let bound = match checked_item(env, item, res) {
+            Ok(bound) => bound,
+            Err(error) => {
+                check.errors.push((error, item_span(item)));
+                false
+            }
+        };
+        item_signatures(env, item, bound, &mut check.signatures);
+    });
+    if let Err(error) = res {
+        check.errors.push((error, None));
+    }
+    check.diagnostics = env.take_diagnostics();
+    match lint_config {
+        Ok(config) => (check.diagnostics).extend(lint(&items, &mut env, &config)),
+        Err(error) => check.errors.push((error, None)),
+    }
+    check
+}
+
+fn check_env() -> Uiua {
+    Uiua::with_backend(CheckSys)
+        .with_execution_limit(CHECK_TIME_LIMIT)
+        .with_memory_limit(CHECK_MEMORY_LIMIT)
+}
+
+/// Handle the result of checking an item, returning whether it ran or was bound
+///
+/// Bindings that need system functions are bound without being run.
+fn checked_item(env: &mut Uiua, item: &Item, res: UiuaResult) -> UiuaResult<bool> {
+    match res {
+        Ok(()) => Ok(true),
+        Err(error) if error_root_message(&error) == Some(CHECK_REFUSAL) => Ok(match item {
+            Item::Binding(binding) => env.bind_unevaluated(binding.clone()).is_ok(),
+            _ => false,
+        }),
+        Err(error) => Err(error),
+    }
+}
+
+/// A backend that refuses to run system functions, so that code can be checked without side effects
+///
+/// Imports are still allowed so that imported bindings can be checked.
+struct CheckSys;
+
+impl SysBackend for CheckSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn print_str_trace(&self, _: &str) {}
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        fs::read(path).map_err(|e| e.to_string())
+    }
+    fn check_sys_op(&self, op: SysOp) -> Result<(), String> {
+        if op == SysOp::Import {
+            Ok(())
+        } else {
+            Err(CHECK_REFUSAL.into())
+        }
+    }
+}
+
+fn error_root_message(error: &UiuaError) -> Option<&str> {
+    match error {
+        UiuaError::Run(message) => Some(&message.value),
+        UiuaError::Traced { error, .. } | UiuaError::Fill(error) => error_root_message(error),
+        _ => None,
+    }
+}
+
+fn item_span(item: &Item) -> Option<CodeSpan> {
+    match item {
+        Item::TestScope(items) => Some(items.span.clone()),
+        Item::Words(words) => words_code_span(words),
+        Item::Binding(binding) => Some(binding.name.span.clone()),
+        Item::ExtraNewlines(span) => Some(span.clone()),
+    }
+}
+
+fn words_code_span(words: &[Sp<Word>]) -> Option<CodeSpan> {
+    let mut code = words.iter().filter(|word| word.value.is_code());
+    let first = code.next()?.span.clone();
+    Some(match code.next_back() {
+        Some(last) => first.merge(last.span.clone()),
+        None => first,
+    })
+}
+
+fn item_signatures(env: &mut Uiua, item: &Item, bound: bool, hints: &mut Vec<SignatureHint>) {
+    match item {
+        Item::Words(words) => {
+            let Some(span) = words_code_span(words) else {
+                return;
+            };
+            if let Some(signature) = env.words_signature(words.clone()) {
+                hints.push(SignatureHint {
+                    span,
+                    signature,
+                    kind: SignatureHintKind::Line,
+                });
+            }
+            nested_signatures(env, words, hints);
+        }
+        Item::Binding(binding) => {
+            let signature = bound
+                .then(|| env.binding_signature(&binding.name.value))
+                .flatten();
+            if let Some(signature) = signature {
+                let end = words_code_span(&binding.words).unwrap_or(binding.arrow_span.clone());
+                hints.push(SignatureHint {
+                    span: binding.name.span.clone().merge(end),
+                    signature,
+                    kind: SignatureHintKind::Binding,
+                });
+            }
+            nested_signatures(env, &binding.words, hints);
+        }
+        Item::TestScope(_) | Item::ExtraNewlines(_) => {}
+    }
+}
+
+fn nested_signatures(env: &mut Uiua, words: &[Sp<Word>], hints: &mut Vec<SignatureHint>) {
+    let mut hint = |env: &mut Uiua, word: &Sp<Word>, kind: SignatureHintKind| {
+        if let Some(signature) = env.word_function_signature(word.clone()) {
+            hints.push(SignatureHint {
+                span: word.span.clone(),
+                signature,
+                kind,
+            });
+        }
+    };
+    let mut inner: Vec<&[Sp<Word>]> = Vec::new();
+    for word in words {
+        match &word.value {
+            Word::Func(func) => {
+                hint(env, word, SignatureHintKind::Function);
+                inner.extend(func.lines.iter().map(Vec::as_slice));
+            }
+            Word::Switch(sw) => {
+                for branch in &sw.branches {
+                    let branch_word = branch.span.clone().sp(Word::Func(branch.value.clone()));
+                    hint(env, &branch_word, SignatureHintKind::Branch);
+                    inner.extend(branch.value.lines.iter().map(Vec::as_slice));
+                }
+            }
+            Word::Modified(m) => {
+                for operand in m.code_operands() {
+                    hint(env, operand, SignatureHintKind::Operand);
+                    match &operand.value {
+                        Word::Func(func) => inner.extend(func.lines.iter().map(Vec::as_slice)),
+                        _ => inner.push(slice::from_ref(operand)),
+                    }
+                }
+            }
+            Word::Strand(words) => inner.push(words),
+            Word::Array(arr) => inner.extend(arr.lines.iter().map(Vec::as_slice)),
+            _ => {}
+        }
+    }
+    for line in inner {
+        nested_signatures(env, line, hints);
+    }
+}
+
 #[cfg(feature = "lsp")]
 #[doc(hidden)]
 pub use server::run_language_server;
 
 #[cfg(feature = "lsp")]
 mod server {
-    use std::{collections::BTreeMap, sync::Arc};
+    use std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        fs,
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::SystemTime,
+    };
 
     use dashmap::DashMap;
-    use tower_lsp::{jsonrpc::Result, lsp_types::*, *};
+    use parking_lot::Mutex;
+    use tower_lsp::{
+        jsonrpc::Result,
+        lsp_types::{Diagnostic, *},
+        *,
+    };
 
     use super::*;
 
     use crate::{
+        ast::{Binding, Modified},
         format::{format_str, FormatConfig},
-        lex::Loc,
+        lex::{is_ident_char, lex, Loc, Token},
+        lint::LintConfigCache,
+        parse::{
+            binding_name_case_advice, binding_name_modifier_args_problem, ident_modifier_args,
+        },
         primitive::{PrimClass, PrimDocFragment},
-        Ident, Uiua,
+        DiagnosticKind, Ident, Span,
     };
 
     pub struct LspDoc {
@@ -177,20 +479,27 @@ mod server {
         pub items: Vec<Item>,
         pub spans: Vec<Sp<SpanKind>>,
         pub bindings: BindingsInfo,
+        pub path: Option<PathBuf>,
+        pub check: CodeCheck,
     }
 
     type BindingsInfo = BTreeMap<Sp<Ident>, Arc<BindingInfo>>;
 
     impl LspDoc {
-        fn new(input: String) -> Self {
+        fn new(input: String, uri: &Url, lint_configs: &Mutex<LintConfigCache>) -> Self {
             let (items, _, _) = parse(&input, None);
             let spans = items_spans(&items);
             let bindings = bindings_info(&items);
+            let path = uri.to_file_path().ok();
+            let lint_config = lint_configs.lock().get(path.as_deref());
+            let check = check_code_static_with(&input, path.as_deref(), lint_config);
             Self {
                 input,
                 items,
                 spans,
                 bindings,
+                path,
+                check,
             }
         }
     }
@@ -198,15 +507,98 @@ mod server {
     pub struct BindingInfo {
         pub span: CodeSpan,
         pub comment: Option<String>,
+        pub signature: Option<Signature>,
+        /// The path and item name if the binding is an import
+        pub import: Option<(String, Sp<String>)>,
+    }
+
+    /// Get the path and item name of a binding of the form `&i "path" "Name"`
+    fn import_target(words: &[Sp<Word>]) -> Option<(String, Sp<String>)> {
+        let mut words = words.iter().filter(|word| word.value.is_code());
+        let (
+            Some(Word::Primitive(Primitive::Sys(SysOp::Import))),
+            Some(Word::String(path)),
+            Some(name),
+            None,
+        ) = (
+            words.next().map(|w| &w.value),
+            words.next().map(|w| &w.value),
+            words.next(),
+            words.next(),
+        )
+        else {
+            return None;
+        };
+        let Word::String(item) = &name.value else {
+            return None;
+        };
+        Some((path.clone(), name.span.clone().sp(item.clone())))
+    }
+
+    /// Record references to known bindings in some words
+    fn binding_references(bindings: &mut BindingsInfo, words: &[Sp<Word>]) {
+        let reference = |bindings: &mut BindingsInfo, ident: Sp<Ident>| {
+            if let Some((_, info)) =
+                (bindings.iter().rev()).find(|(name, _)| name.value == ident.value)
+            {
+                let info = info.clone();
+                bindings.insert(ident, info);
+            }
+        };
+        for word in words {
+            match &word.value {
+                Word::Ident(ident) => reference(bindings, word.span.clone().sp(ident.clone())),
+                Word::Strand(words) => binding_references(bindings, words),
+                Word::Array(arr) => {
+                    for line in &arr.lines {
+                        binding_references(bindings, line);
+                    }
+                }
+                Word::Func(func) => {
+                    for line in &func.lines {
+                        binding_references(bindings, line);
+                    }
+                }
+                Word::Switch(sw) => {
+                    for branch in &sw.branches {
+                        for line in &branch.value.lines {
+                            binding_references(bindings, line);
+                        }
+                    }
+                }
+                Word::Modified(m) => {
+                    if let Modifier::Ident(ident) = &m.modifier.value {
+                        reference(bindings, m.modifier.span.clone().sp(ident.clone()));
+                    }
+                    binding_references(bindings, &m.operands);
+                }
+                _ => {}
+            }
+        }
     }
 
     fn bindings_info(items: &[Item]) -> BindingsInfo {
-        let mut bindings = BindingsInfo::new();
+        scope_bindings_info(items, BindingsInfo::new())
+    }
+
+    /// Get the bindings of some items, starting with the bindings of the enclosing scope
+    fn scope_bindings_info(items: &[Item], mut bindings: BindingsInfo) -> BindingsInfo {
         let mut scope_bindings = Vec::new();
         let mut last_comment: Option<String> = None;
         for item in items {
             match item {
-                Item::TestScope(items) => scope_bindings.push(bindings_info(&items.value)),
+                Item::TestScope(items) => {
+                    let outer: HashSet<*const BindingInfo> =
+                        bindings.values().map(Arc::as_ptr).collect();
+                    let inner = scope_bindings_info(&items.value, bindings.clone());
+                    // References to outer bindings are visible after the scope,
+                    // but the scope's own bindings are not
+                    let (outer_refs, local): (BindingsInfo, BindingsInfo) = (inner.into_iter())
+                        .filter(|(ident, _)| !bindings.contains_key(ident))
+                        .partition(|(_, info)| outer.contains(&Arc::as_ptr(info)));
+                    bindings.extend(outer_refs);
+                    scope_bindings.push(local);
+                }
                 Item::Words(words) => {
                     if let [Sp {
                         value: Word::Comment(comment),
@@ -225,25 +617,19 @@ mod server {
                         full.push_str(comment.trim());
                     } else {
                         last_comment = None;
-                        for word in words {
-                            if let Word::Ident(ident) = &word.value {
-                                if let Some((_, info)) =
-                                    bindings.iter().rev().find(|(name, _)| name.value == *ident)
-                                {
-                                    let info = info.clone();
-                                    bindings.insert(word.span.clone().sp(ident.clone()), info);
-                                }
-                            }
-                        }
+                        binding_references(&mut bindings, words);
                     }
                 }
                 Item::Binding(binding) => {
                     let comment = last_comment.take();
+                    binding_references(&mut bindings, &binding.words);
                     bindings.insert(
                         binding.name.clone(),
                         BindingInfo {
                             comment,
                             span: binding.name.span.clone(),
+                            signature: binding.signature.as_ref().map(|sig| sig.value),
+                            import: import_target(&binding.words),
                         }
                         .into(),
                     );
@@ -255,6 +641,891 @@ mod server {
         scope_bindings.into_iter().flatten().collect()
     }
 
+    /// How many imports to follow before giving up, in case of cycles
+    const MAX_IMPORT_DEPTH: usize = 32;
+
+    /// The `.ua` files in the workspace folders and the bindings of files that are not open
+    ///
+    /// This is kept between requests. Files are only parsed again when they are modified,
+    /// and the workspace folders are only searched again when a file is created or deleted.
+    #[derive(Default)]
+    struct FileIndex {
+        /// The files in the workspace folders, found the first time they are needed
+        paths: Option<Vec<Url>>,
+        bindings: HashMap<Url, (Option<SystemTime>, Option<Arc<BindingsInfo>>)>,
+    }
+
+    impl FileIndex {
+        fn bindings(&mut self, uri: &Url) -> Option<Arc<BindingsInfo>> {
+            let path = uri.to_file_path().ok()?;
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+            if let Some((cached, bindings)) = self.bindings.get(uri) {
+                if modified.is_some() && *cached == modified {
+                    return bindings.clone();
+                }
+            }
+            let bindings = fs::read_to_string(&path).ok().map(|input| {
+                let (items, _, _) = parse(&input, Some(&path));
+                Arc::new(bindings_info(&items))
+            });
+            (self.bindings).insert(uri.clone(), (modified, bindings.clone()));
+            bindings
+        }
+        fn paths(&mut self, roots: &[PathBuf]) -> Vec<Url> {
+            (self.paths.get_or_insert_with(|| {
+                let mut paths = Vec::new();
+                for root in roots {
+                    find_uiua_files(root, &mut paths);
+                }
+                (paths.into_iter())
+                    .filter_map(|path| Url::from_file_path(path).ok())
+                    .map(|uri| canonical_uri(&uri))
+                    .collect()
+            }))
+            .clone()
+        }
+        /// Forget about a file that was created, changed, or deleted
+        fn file_changed(&mut self, uri: &Url, change: FileChangeType) {
+            self.bindings.remove(uri);
+            if change != FileChangeType::CHANGED {
+                self.paths = None;
+            }
+        }
+    }
+
+    /// The bindings of open documents and the files around them
+    ///
+    /// Files are identified by canonical URIs.
+    struct Workspace<'a> {
+        /// The URIs the client knows open documents by
+        open: HashMap<Url, Url>,
+        /// The bindings of the files used so far in this request
+        files: HashMap<Url, Option<Arc<BindingsInfo>>>,
+        roots: Vec<PathBuf>,
+        index: &'a Mutex<FileIndex>,
+    }
+
+    impl<'a> Workspace<'a> {
+        fn new(
+            docs: &DashMap<Url, LspDoc>,
+            roots: &[PathBuf],
+            index: &'a Mutex<FileIndex>,
+        ) -> Self {
+            let mut open = HashMap::new();
+            let mut files = HashMap::new();
+            for entry in docs.iter() {
+                let uri = canonical_uri(entry.key());
+                files.insert(uri.clone(), Some(Arc::new(entry.bindings.clone())));
+                open.insert(uri, entry.key().clone());
+            }
+            Self {
+                open,
+                files,
+                roots: roots.into(),
+                index,
+            }
+        }
+        fn bindings(&mut self, uri: &Url) -> Option<Arc<BindingsInfo>> {
+            let index = self.index;
+            (self.files.entry(uri.clone()))
+                .or_insert_with(|| index.lock().bindings(uri))
+                .clone()
+        }
+        /// Get the URI the client knows a file by
+        fn client_uri(&self, uri: &Url) -> Url {
+            self.open.get(uri).unwrap_or(uri).clone()
+        }
+        fn binding_at(
+            &mut self,
+            uri: &Url,
+            position: Position,
+        ) -> Option<(Sp<Ident>, Arc<BindingInfo>)> {
+            let bindings = self.bindings(uri)?;
+            // All of a file's bindings are identifiers in that file
+            let input = bindings.keys().next()?.span.input.clone();
+            let (line, col) = lsp_pos_to_uiua(&input, position);
+            let (ident, info) =
+                (bindings.iter()).find(|(ident, _)| ident.span.contains_line_col(line, col))?;
+            Some((ident.clone(), info.clone()))
+        }
+        /// Get the definition of an item in an imported file
+        ///
+        /// Import paths are resolved relative to the importing file.
+        fn import(
+            &mut self,
+            from: &Url,
+            path: &str,
+            name: &str,
+        ) -> Option<(Url, Arc<BindingInfo>)> {
+            let path = from.to_file_path().ok()?.parent()?.join(path);
+            let uri = canonical_uri(&Url::from_file_path(path).ok()?);
+            let bindings = self.bindings(&uri)?;
+            let (_, info) = (bindings.iter().rev())
+                .find(|(ident, info)| &*ident.value == name && info.span == ident.span)?;
+            Some((uri, info.clone()))
+        }
+        /// Follow imports to find where a binding is defined
+        ///
+        /// If `same_name` is set, items imported under a different name are not followed.
+        fn resolve(
+            &mut self,
+            uri: &Url,
+            name: &str,
+            info: &Arc<BindingInfo>,
+            same_name: bool,
+        ) -> (Url, Arc<BindingInfo>) {
+            let (mut uri, mut name, mut info) = (uri.clone(), name.to_string(), info.clone());
+            for _ in 0..MAX_IMPORT_DEPTH {
+                let Some((path, item)) = info.import.clone() else {
+                    break;
+                };
+                if same_name && item.value != name {
+                    break;
+                }
+                let Some(next) = self.import(&uri, &path, &item.value) else {
+                    break;
+                };
+                (uri, info) = next;
+                name = item.value;
+            }
+            (uri, info)
+        }
+        /// Find all the names that resolve to a definition
+        fn references(
+            &mut self,
+            target_uri: &Url,
+            target: &Arc<BindingInfo>,
+            same_name: bool,
+        ) -> Vec<(Url, Sp<Ident>, Arc<BindingInfo>)> {
+            let mut references = Vec::new();
+            for uri in self.files_to_search(target_uri) {
+                let Some(bindings) = self.bindings(&uri) else {
+                    continue;
+                };
+                for (ident, info) in bindings.iter() {
+                    let (def_uri, def) = self.resolve(&uri, &ident.value, info, same_name);
+                    if def_uri == *target_uri && Arc::ptr_eq(&def, target) {
+                        references.push((uri.clone(), ident.clone(), info.clone()));
+                    }
+                }
+            }
+            references
+        }
+        /// Find the item name strings of imports that resolve to a definition
+        fn import_references(
+            &mut self,
+            target_uri: &Url,
+            target: &Arc<BindingInfo>,
+        ) -> Vec<(Url, CodeSpan)> {
+            let mut references = Vec::new();
+            for uri in self.files_to_search(target_uri) {
+                let Some(bindings) = self.bindings(&uri) else {
+                    continue;
+                };
+                for (ident, info) in bindings.iter() {
+                    let Some((path, item)) =
+                        (info.import.as_ref()).filter(|_| ident.span == info.span)
+                    else {
+                        continue;
+                    };
+                    let Some((imported_uri, imported)) = self.import(&uri, path, &item.value)
+                    else {
+                        continue;
+                    };
+                    let (def_uri, def) = self.resolve(&imported_uri, &item.value, &imported, true);
+                    if def_uri == *target_uri && Arc::ptr_eq(&def, target) {
+                        references.push((uri.clone(), item.span.clone()));
+                    }
+                }
+            }
+            references
+        }
+        /// Get the open documents, the files in the workspace folders, and the target file
+        fn files_to_search(&mut self, target_uri: &Url) -> Vec<Url> {
+            let mut files: Vec<Url> = self.open.keys().cloned().collect();
+            let paths = self.index.lock().paths(&self.roots);
+            for uri in paths.into_iter().chain([target_uri.clone()]) {
+                if !files.contains(&uri) {
+                    files.push(uri);
+                }
+            }
+            files
+        }
+    }
+
+    fn canonical_uri(uri: &Url) -> Url {
+        (uri.to_file_path().ok())
+            .and_then(|path| fs::canonicalize(path).ok())
+            .and_then(|path| Url::from_file_path(path).ok())
+            .unwrap_or_else(|| uri.clone())
+    }
+
+    fn find_uiua_files(dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden =
+                (path.file_name()).is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                find_uiua_files(&path, files);
+            } else if path.extension().is_some_and(|ext| ext == "ua") {
+                files.push(path);
+            }
+        }
+    }
+
+    /// Check that a binding can be renamed, returning the problem if it can't
+    ///
+    /// The new name must be lexed as a single identifier and satisfy the same checks
+    /// as the names of new bindings.
+    fn rename_problem(old: &str, new: &str) -> Option<String> {
+        let (tokens, errors) = lex(new, None);
+        match tokens.as_slice() {
+            [token]
+                if errors.is_empty()
+                    && token.value == Token::Ident
+                    && token.span.as_str() == new
+                    && !new.starts_with('&') => {}
+            tokens if (tokens.iter()).any(|token| matches!(token.value, Token::Glyph(_))) => {
+                return Some(format!("`{new}` would be formatted as primitives"))
+            }
+            _ => return Some(format!("`{new}` is not a valid binding name")),
+        }
+        let old_margs = ident_modifier_args(&old.into()) as usize;
+        binding_name_modifier_args_problem(new, old_margs).or_else(|| binding_name_case_advice(new))
+    }
+
+    /// Where a word appears, which determines how it can be replaced
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum WordPosition {
+        Line,
+        Operand,
+        Strand,
+    }
+
+    /// Collect the identifiers in some words along with their positions
+    fn ident_positions(
+        words: &[Sp<Word>],
+        position: WordPosition,
+        idents: &mut Vec<(CodeSpan, WordPosition)>,
+    ) {
+        for word in words {
+            match &word.value {
+                Word::Ident(_) => idents.push((word.span.clone(), position)),
+                Word::Strand(words) => ident_positions(words, WordPosition::Strand, idents),
+                Word::Array(arr) => {
+                    for line in &arr.lines {
+                        ident_positions(line, WordPosition::Line, idents);
+                    }
+                }
+                Word::Func(func) => {
+                    for line in &func.lines {
+                        ident_positions(line, WordPosition::Line, idents);
+                    }
+                }
+                Word::Switch(sw) => {
+                    for branch in &sw.branches {
+                        for line in &branch.value.lines {
+                            ident_positions(line, WordPosition::Line, idents);
+                        }
+                    }
+                }
+                Word::Modified(m) => ident_positions(&m.operands, WordPosition::Operand, idents),
+                _ => {}
+            }
+        }
+    }
+
+    fn items_ident_positions(items: &[Item], idents: &mut Vec<(CodeSpan, WordPosition)>) {
+        for item in items {
+            match item {
+                Item::Words(words) => ident_positions(words, WordPosition::Line, idents),
+                Item::Binding(binding) => {
+                    ident_positions(&binding.words, WordPosition::Line, idents)
+                }
+                Item::TestScope(items) => items_ident_positions(&items.value, idents),
+                Item::ExtraNewlines(_) => {}
+            }
+        }
+    }
+
+    fn find_binding<'a>(items: &'a [Item], name: &CodeSpan) -> Option<&'a Binding> {
+        items.iter().find_map(|item| match item {
+            Item::Binding(binding) if binding.name.span == *name => Some(binding),
+            Item::TestScope(items) => find_binding(&items.value, name),
+            _ => None,
+        })
+    }
+
+    /// Get the code that replaces a reference to a binding when it is inlined
+    fn inlined_code(binding: &Binding, position: WordPosition) -> Option<String> {
+        let code: Vec<&Sp<Word>> = (binding.words.iter())
+            .filter(|word| word.value.is_code())
+            .collect();
+        let span = words_code_span(&binding.words)?;
+        if let [word] = code.as_slice() {
+            return match (&word.value, position) {
+                // Inline functions are only allowed as operands, so unwrap them elsewhere
+                (Word::Func(func), WordPosition::Line)
+                    if func.signature.is_none() && func.lines.len() == 1 =>
+                {
+                    Some(words_code_span(&func.lines[0])?.as_str().into())
+                }
+                (Word::Func(_), WordPosition::Line) => None,
+                _ => Some(span.as_str().into()),
+            };
+        }
+        match position {
+            WordPosition::Line => Some(span.as_str().into()),
+            WordPosition::Operand => Some(match &binding.signature {
+                Some(sig) => format!("({} {})", sig.value, span.as_str()),
+                None => format!("({})", span.as_str()),
+            }),
+            WordPosition::Strand => None,
+        }
+    }
+
+    /// Get the edits that replace the references to a binding with its code and remove it
+    ///
+    /// Bindings that are imported by other files are not inlined.
+    fn inline_binding_edits(
+        doc: &LspDoc,
+        uri: &Url,
+        position: Position,
+        workspace: &mut Workspace,
+    ) -> Option<(Ident, Vec<TextEdit>)> {
+        let (line, col) = lsp_pos_to_uiua(&doc.input, position);
+        let (_, info) =
+            (doc.bindings.iter()).find(|(ident, _)| ident.span.contains_line_col(line, col))?;
+        if info.import.is_some() {
+            return None;
+        }
+        if (workspace.references(uri, info, false).iter()).any(|(ref_uri, ..)| ref_uri != uri) {
+            return None;
+        }
+        let binding = find_binding(&doc.items, &info.span)?;
+        if ident_modifier_args(&binding.name.value) > 0
+            || (binding.words.iter()).any(|word| matches!(word.value, Word::Placeholder(_)))
+        {
+            return None;
+        }
+        let mut positions = Vec::new();
+        items_ident_positions(&doc.items, &mut positions);
+        let mut edits = Vec::new();
+        for (ident, ref_info) in &doc.bindings {
+            if !Arc::ptr_eq(ref_info, info) || ident.span == info.span {
+                continue;
+            }
+            let position = (positions.iter())
+                .find(|(span, _)| *span == ident.span)
+                .map_or(WordPosition::Line, |(_, position)| *position);
+            let code = inlined_code(binding, position)?;
+            edits.push(TextEdit::new(uiua_span_to_lsp(&ident.span), code));
+        }
+        if edits.is_empty() {
+            return None;
+        }
+        let span = binding.span();
+        edits.push(TextEdit::new(
+            Range::new(
+                Position::new(span.start.line as u32 - 1, 0),
+                Position::new(span.end.line as u32, 0),
+            ),
+            String::new(),
+        ));
+        Some((binding.name.value.clone(), edits))
+    }
+
+    /// Find the contiguous code words that a selection covers
+    ///
+    /// If the selection is inside a single word, the words inside it are searched.
+    fn selected_words(
+        words: &[Sp<Word>],
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> Option<&[Sp<Word>]> {
+        let loc = |loc: Loc| (loc.line, loc.col);
+        let mut inside = Vec::new();
+        let mut partial = Vec::new();
+        for (i, word) in words.iter().enumerate() {
+            if !word.value.is_code() {
+                continue;
+            }
+            let (word_start, word_end) = (loc(word.span.start), loc(word.span.end));
+            if word_start >= start && word_end <= end {
+                inside.push(i);
+            } else if word_end > start && word_start < end {
+                partial.push(word);
+            }
+        }
+        match (inside.as_slice(), partial.as_slice()) {
+            ([first, .., last], []) => Some(&words[*first..=*last]),
+            ([only], []) => Some(&words[*only..=*only]),
+            ([], [word]) => {
+                let lines: Vec<&[Sp<Word>]> = match &word.value {
+                    Word::Func(func) => func.lines.iter().map(Vec::as_slice).collect(),
+                    Word::Array(arr) => arr.lines.iter().map(Vec::as_slice).collect(),
+                    Word::Switch(sw) => (sw.branches.iter())
+                        .flat_map(|branch| branch.value.lines.iter().map(Vec::as_slice))
+                        .collect(),
+                    Word::Modified(m) => vec![m.operands.as_slice()],
+                    _ => Vec::new(),
+                };
+                lines
+                    .into_iter()
+                    .find_map(|line| selected_words(line, start, end))
+            }
+            _ => None,
+        }
+    }
+
+    /// Infer the signature of some words using the bindings defined before an item
+    fn words_signature_before(
+        doc: &LspDoc,
+        scope: Option<&CodeSpan>,
+        item_span: &CodeSpan,
+        words: &[Sp<Word>],
+    ) -> Option<Signature> {
+        let mut env = check_env();
+        let item_start = item_span.start.byte_pos;
+        let before = if let Some(scope) = scope {
+            // The scope's earlier items are checked without the opening `---`
+            // so that their bindings are still in view
+            let scope_start = scope.start.byte_pos;
+            let body_start = scope_start + "---".len();
+            format!(
+                "{}{}",
+                &doc.input[..scope_start],
+                &doc.input[body_start..item_start]
+            )
+        } else {
+            doc.input[..item_start].into()
+        };
+        env.check_str(&before, doc.path.as_deref(), |env, item, res| {
+            _ = checked_item(env, item, res);
+        })
+        .ok()?;
+        env.words_signature(words.to_vec())
+    }
+
+    /// The span of the test scope a selection is in, if any,
+    /// the item that contains it, and the words it covers
+    type SelectedWords<'a> = (Option<&'a CodeSpan>, &'a Item, &'a [Sp<Word>]);
+
+    /// Find the item that contains a selection and the words it covers
+    fn selected_item_words(
+        items: &[Item],
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> Option<SelectedWords<'_>> {
+        items.iter().find_map(|item| match item {
+            Item::Words(words) => Some((None, item, selected_words(words, start, end)?)),
+            Item::Binding(binding) => {
+                Some((None, item, selected_words(&binding.words, start, end)?))
+            }
+            Item::TestScope(items) => selected_item_words(&items.value, start, end)
+                .map(|(_, item, words)| (Some(&items.span), item, words)),
+            Item::ExtraNewlines(_) => None,
+        })
+    }
+
+    /// Get the edits that move the selected words into a new binding
+    fn extract_binding_edits(doc: &LspDoc, range: Range) -> Option<(Ident, Vec<TextEdit>)> {
+        let (start, end) = (
+            lsp_pos_to_uiua(&doc.input, range.start),
+            lsp_pos_to_uiua(&doc.input, range.end),
+        );
+        let (scope, item, words) = selected_item_words(&doc.items, start, end)?;
+        if (words.iter()).any(|word| matches!(word.value, Word::Placeholder(_))) {
+            return None;
+        }
+        let span = words_code_span(words)?;
+        let name: Ident = (1..)
+            .map(|i| match i {
+                1 => "Extracted".to_string(),
+                i => format!("Extracted{i}"),
+            })
+            .find(|name| !doc.bindings.keys().any(|ident| &*ident.value == name))?
+            .into();
+        let item_span = item.span();
+        let binding = match words_signature_before(doc, scope, &item_span, words) {
+            Some(sig) => format!("{name} ← {sig} {}\n", span.as_str()),
+            None => format!("{name} ← {}\n", span.as_str()),
+        };
+        let item_start = Position::new(item_span.start.line as u32 - 1, 0);
+        Some((
+            name.clone(),
+            vec![
+                TextEdit::new(Range::new(item_start, item_start), binding),
+                TextEdit::new(uiua_span_to_lsp(&span), name.to_string()),
+            ],
+        ))
+    }
+
+    /// Get the edit that formats the code in a range
+    ///
+    /// The whole document is formatted, and the range is mapped into the formatted code.
+    fn format_range_edit(doc: &LspDoc, range: Range) -> UiuaResult<Option<TextEdit>> {
+        let (mut start, mut end) = (
+            lsp_pos_to_char_pos(&doc.input, range.start),
+            lsp_pos_to_char_pos(&doc.input, range.end),
+        );
+        // Don't split names
+        for sp in &doc.spans {
+            if let SpanKind::Primitive(_) = sp.value {
+                let span = sp.span.start.char_pos..sp.span.end.char_pos;
+                if span.start < start && start < span.end {
+                    start = span.start;
+                }
+                if span.start < end && end < span.end {
+                    end = span.end;
+                }
+            }
+        }
+        let formatted = format_str(&doc.input, &FormatConfig::find().unwrap_or_default())?;
+        let (_, new_start) = formatted.map_char_pos(start);
+        let (_, new_end) = formatted.map_char_pos(end);
+        let new_text: String = (formatted.output.chars())
+            .skip(new_start)
+            .take(new_end.saturating_sub(new_start))
+            .collect();
+        let old_text: String = doc.input.chars().skip(start).take(end - start).collect();
+        if new_text == old_text {
+            return Ok(None);
+        }
+        let range = Range::new(
+            char_pos_to_lsp(&doc.input, start),
+            char_pos_to_lsp(&doc.input, end),
+        );
+        Ok(Some(TextEdit::new(range, new_text)))
+    }
+
+    /// Get the edit that replaces the glyph names that end at a character position with glyphs
+    ///
+    /// This works even if the code does not parse.
+    fn name_glyphs_edit(doc: &LspDoc, end: usize) -> Option<TextEdit> {
+        let (line, col) = char_pos_to_uiua(&doc.input, end);
+        let in_literal = doc.spans.iter().any(|sp| {
+            matches!(sp.value, SpanKind::String | SpanKind::Comment)
+                && sp.span.contains_line_col(line, col)
+        });
+        if in_literal {
+            return None;
+        }
+        let mut name: Vec<char> = (doc.input.chars().take(end).collect::<Vec<_>>())
+            .into_iter()
+            .rev()
+            .take_while(|&c| is_ident_char(c))
+            .collect();
+        name.reverse();
+        let name: String = name.into_iter().collect();
+        if name.chars().any(char::is_uppercase) {
+            return None;
+        }
+        let prims = Primitive::from_format_name_multi(&name)?;
+        let glyphs: String = prims.iter().map(|(prim, _)| prim.to_string()).collect();
+        let start = end - name.chars().count();
+        let range = Range::new(
+            char_pos_to_lsp(&doc.input, start),
+            char_pos_to_lsp(&doc.input, end),
+        );
+        Some(TextEdit::new(range, glyphs))
+    }
+
+    /// Get the edits that replace the glyphs in a selection with their names
+    fn asciify_edits(doc: &LspDoc, range: Range) -> Vec<TextEdit> {
+        let (start, end) = (
+            lsp_pos_to_char_pos(&doc.input, range.start),
+            lsp_pos_to_char_pos(&doc.input, range.end),
+        );
+        let chars: Vec<char> = doc.input.chars().collect();
+        let mut edits = Vec::new();
+        let mut prev_end = None;
+        for sp in &doc.spans {
+            let SpanKind::Primitive(prim) = sp.value else {
+                continue;
+            };
+            let span = &sp.span;
+            if span.start.char_pos < start || span.end.char_pos > end {
+                continue;
+            }
+            let Some(glyph) = prim.glyph().filter(|glyph| !glyph.is_ascii()) else {
+                continue;
+            };
+            if span.as_str() != glyph.to_string()
+                || Primitive::from_format_name(prim.name()) != Some(prim)
+            {
+                continue;
+            }
+            // Keep names from running into each other
+            let mut name = prim.name().to_string();
+            let before = span.start.char_pos.checked_sub(1).map(|i| chars[i]);
+            if before.is_some_and(is_ident_char) || prev_end == Some(span.start.char_pos) {
+                name.insert(0, ' ');
+            }
+            if chars
+                .get(span.end.char_pos)
+                .copied()
+                .is_some_and(is_ident_char)
+            {
+                name.push(' ');
+            }
+            prev_end = Some(span.end.char_pos);
+            edits.push(TextEdit::new(uiua_span_to_lsp(span), name));
+        }
+        edits
+    }
+
+    fn code_action(
+        title: impl Into<String>,
+        kind: CodeActionKind,
+        uri: &Url,
+        edits: Vec<TextEdit>,
+    ) -> CodeActionOrCommand {
+        CodeActionOrCommand::CodeAction(CodeAction {
+            title: title.into(),
+            kind: Some(kind),
+            edit: Some(WorkspaceEdit::new(HashMap::from([(uri.clone(), edits)]))),
+            ..Default::default()
+        })
+    }
+
+    /// Find the innermost modified word that contains a position
+    fn modified_at(words: &[Sp<Word>], line: usize, col: usize) -> Option<&Modified> {
+        for word in words {
+            if !word.span.contains_line_col(line, col) {
+                continue;
+            }
+            let inner = match &word.value {
+                Word::Strand(words) => modified_at(words, line, col),
+                Word::Array(arr) => {
+                    (arr.lines.iter()).find_map(|line_words| modified_at(line_words, line, col))
+                }
+                Word::Func(func) => {
+                    (func.lines.iter()).find_map(|line_words| modified_at(line_words, line, col))
+                }
+                Word::Switch(sw) => (sw.branches.iter())
+                    .flat_map(|branch| &branch.value.lines)
+                    .find_map(|line_words| modified_at(line_words, line, col)),
+                Word::Modified(m) => modified_at(&m.operands, line, col).or(Some(&**m)),
+                _ => None,
+            };
+            if inner.is_some() {
+                return inner;
+            }
+        }
+        None
+    }
+
+    fn items_modified_at(items: &[Item], line: usize, col: usize) -> Option<&Modified> {
+        items.iter().find_map(|item| match item {
+            Item::Words(words) => modified_at(words, line, col),
+            Item::Binding(binding) => modified_at(&binding.words, line, col),
+            Item::TestScope(items) => items_modified_at(&items.value, line, col),
+            Item::ExtraNewlines(_) => None,
+        })
+    }
+
+    /// Describe the function a modifier expects for each of its operands
+    fn operand_expectations(modifier: &Modifier) -> Vec<(&'static str, &'static str)> {
+        use Primitive::*;
+        let Modifier::Primitive(prim) = modifier else {
+            return vec![("f", "any signature"); modifier.args() as usize];
+        };
+        match prim {
+            Reduce | Scan => vec![("f", "|2.1")],
+            Fold => vec![("f", "|n.m, where n > m")],
+            Each | Rows => vec![("f", "|n.m, where n ≥ 1")],
+            Distribute | Tribute => vec![("f", "|n.m, where n ≥ 2")],
+            Table | Cross => vec![("f", "|2.m")],
+            Group | Partition => vec![("f", "|0.m or |1.m to iterate, |n.m to accumulate")],
+            Invert => vec![("f", "invertible")],
+            SetInverse => vec![("f", "any signature"), ("inverse", "any signature")],
+            SetUnder => vec![
+                ("f", "any signature"),
+                ("before", "any signature"),
+                ("after", "any signature"),
+            ],
+            Under => vec![("f", "invertible"), ("g", "any signature")],
+            Do => vec![
+                ("body", "|n.m"),
+                (
+                    "condition",
+                    "returns at least 1 value, net stack change 0 with body",
+                ),
+            ],
+            Fill => vec![("value", "|0.1"), ("f", "any signature")],
+            Level => vec![("ranks", "|0.1"), ("f", "|n.1, one argument per rank")],
+            Combinate => vec![("ranks", "|0.1"), ("f", "|n.1, one argument per rank")],
+            Try => vec![("f", "|n.m"), ("handler", "|(n+1).m")],
+            prim => vec![("f", "any signature"); prim.modifier_args().unwrap_or(0) as usize],
+        }
+    }
+
+    /// Get the outline of some items
+    fn item_symbols(items: &[Item], check: &CodeCheck) -> Vec<DocumentSymbol> {
+        let mut symbols = Vec::new();
+        for item in items {
+            match item {
+                Item::Binding(binding) => {
+                    let signature = (check.signatures.iter())
+                        .find(|hint| {
+                            hint.kind == SignatureHintKind::Binding
+                                && hint.span.start == binding.name.span.start
+                        })
+                        .map(|hint| hint.signature)
+                        .or(binding.signature.as_ref().map(|sig| sig.value));
+                    let (kind, detail) = if let Some((path, _)) = import_target(&binding.words) {
+                        (SymbolKind::MODULE, Some(format!("&i {path:?}")))
+                    } else if ident_modifier_args(&binding.name.value) > 0 {
+                        (SymbolKind::OPERATOR, signature.map(|sig| sig.to_string()))
+                    } else if signature == Some(Signature::new(0, 1)) {
+                        (SymbolKind::CONSTANT, signature.map(|sig| sig.to_string()))
+                    } else {
+                        (SymbolKind::FUNCTION, signature.map(|sig| sig.to_string()))
+                    };
+                    #[allow(deprecated)]
+                    symbols.push(DocumentSymbol {
+                        name: binding.name.value.to_string(),
+                        detail,
+                        kind,
+                        tags: None,
+                        deprecated: None,
+                        range: uiua_span_to_lsp(&binding.span()),
+                        selection_range: uiua_span_to_lsp(&binding.name.span),
+                        children: None,
+                    });
+                }
+                Item::TestScope(scope) => {
+                    #[allow(deprecated)]
+                    symbols.push(DocumentSymbol {
+                        name: "---".into(),
+                        detail: Some("test scope".into()),
+                        kind: SymbolKind::NAMESPACE,
+                        tags: None,
+                        deprecated: None,
+                        range: uiua_span_to_lsp(&scope.span),
+                        selection_range: uiua_span_to_lsp(&scope.span.just_start()),
+                        children: Some(item_symbols(&scope.value, check)),
+                    });
+                }
+                Item::Words(_) | Item::ExtraNewlines(_) => {}
+            }
+        }
+        symbols
+    }
+
+    /// Convert the results of a check into diagnostics
+    fn check_diagnostics(check: &CodeCheck, path: Option<&Path>) -> Vec<Diagnostic> {
+        let in_file = |span: &CodeSpan| span.path.as_deref() == path;
+        let mut diagnostics = Vec::new();
+        for (error, item_span) in &check.errors {
+            error_diagnostics(error, &in_file, item_span.clone(), &mut diagnostics);
+        }
+        for diag in &check.diagnostics {
+            let Span::Code(span) = &diag.span else {
+                continue;
+            };
+            if !in_file(span) {
+                continue;
+            }
+            let severity = match diag.kind {
+                DiagnosticKind::Warning => DiagnosticSeverity::WARNING,
+                DiagnosticKind::Advice => DiagnosticSeverity::INFORMATION,
+                DiagnosticKind::Style => DiagnosticSeverity::HINT,
+            };
+            diagnostics.push(Diagnostic {
+                range: uiua_span_to_lsp(span),
+                severity: Some(severity),
+                source: Some("uiua".into()),
+                message: diag.message.clone(),
+                ..Default::default()
+            });
+        }
+        diagnostics
+    }
+
+    /// Convert an error into diagnostics
+    ///
+    /// Errors that happen in other files are shown at the first span of their trace that is
+    /// in this file, or at the fallback span.
+    fn error_diagnostics(
+        error: &UiuaError,
+        in_file: &impl Fn(&CodeSpan) -> bool,
+        fallback: Option<CodeSpan>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        fn parts(error: &UiuaError, spans: &mut Vec<Span>) -> String {
+            match error {
+                UiuaError::Run(message) => {
+                    spans.push(message.span.clone());
+                    message.value.clone()
+                }
+                UiuaError::Traced { error, trace } => {
+                    let message = parts(error, spans);
+                    spans.extend(trace.iter().map(|frame| frame.span.clone()));
+                    message
+                }
+                UiuaError::Fill(error) => parts(error, spans),
+                UiuaError::Throw(value, span) => {
+                    spans.push(span.clone());
+                    value.to_string()
+                }
+                UiuaError::Timeout(span) | UiuaError::Interrupted(span) => {
+                    spans.push(span.clone());
+                    error.to_string()
+                }
+                UiuaError::Parse(_) | UiuaError::Load(..) | UiuaError::Format(..) => {
+                    error.to_string()
+                }
+            }
+        }
+        let mut push = |message: String, spans: Vec<Span>| {
+            let code_spans: Vec<&CodeSpan> = (spans.iter())
+                .filter_map(|span| match span {
+                    Span::Code(span) => Some(span),
+                    Span::Builtin => None,
+                })
+                .collect();
+            let (span, message) = match code_spans.iter().find(|span| in_file(span)) {
+                Some(span) => ((*span).clone(), message),
+                None => {
+                    let Some(fallback) = fallback.clone() else {
+                        return;
+                    };
+                    match code_spans.first() {
+                        Some(span) => (fallback, format!("{message}\n  at {span}")),
+                        None => (fallback, message),
+                    }
+                }
+            };
+            diagnostics.push(Diagnostic {
+                range: uiua_span_to_lsp(&span),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("uiua".into()),
+                message,
+                ..Default::default()
+            });
+        };
+        match error {
+            UiuaError::Parse(errors) => {
+                for error in errors {
+                    push(error.value.to_string(), vec![error.span.clone().into()]);
+                }
+            }
+            error => {
+                let mut spans = Vec::new();
+                let message = parts(error, &mut spans);
+                push(message, spans);
+            }
+        }
+    }
+
     #[doc(hidden)]
     pub fn run_language_server() {
         tokio::runtime::Builder::new_current_thread()
@@ -269,6 +1540,11 @@ mod server {
                 let (service, socket) = LspService::new(|client| Backend {
                     client,
                     docs: DashMap::new(),
+                    roots: Mutex::new(Vec::new()),
+                    nested_signature_hints: AtomicBool::new(false),
+                    lint_configs: Mutex::new(LintConfigCache::default()),
+                    watch_files: AtomicBool::new(false),
+                    file_index: Mutex::new(FileIndex::default()),
                 });
                 Server::new(stdin, stdout, socket).serve(service).await;
             });
@@ -277,8 +1553,59 @@ mod server {
     struct Backend {
         client: Client,
         docs: DashMap<Url, LspDoc>,
+        /// The workspace folders, which are searched for references
+        roots: Mutex<Vec<PathBuf>>,
+        /// Whether to show signature hints for functions inside bindings and lines
+        nested_signature_hints: AtomicBool,
+        lint_configs: Mutex<LintConfigCache>,
+        /// Whether the client can watch `.lint.ua` and other `.ua` files for us
+        watch_files: AtomicBool,
+        file_index: Mutex<FileIndex>,
+    }
+
+    impl Backend {
+        fn workspace(&self) -> Workspace<'_> {
+            Workspace::new(&self.docs, &self.roots.lock(), &self.file_index)
+        }
+        async fn update_doc(&self, uri: Url, input: String, version: Option<i32>) {
+            let doc = LspDoc::new(input, &uri, &self.lint_configs);
+            let diagnostics = check_diagnostics(&doc.check, doc.path.as_deref());
+            self.docs.insert(uri.clone(), doc);
+            self.client
+                .publish_diagnostics(uri, diagnostics, version)
+                .await;
+        }
+        /// Ask the client to notify us when a `.ua` file is created, changed, or deleted
+        async fn register_file_watcher(&self) {
+            let options = DidChangeWatchedFilesRegistrationOptions {
+                watchers: ["**/*.ua", "**/.lint.ua"]
+                    .map(|glob| FileSystemWatcher {
+                        glob_pattern: GlobPattern::String(glob.into()),
+                        kind: None,
+                    })
+                    .into(),
+            };
+            let registration = Registration {
+                id: "uiua-files".into(),
+                method: "workspace/didChangeWatchedFiles".into(),
+                register_options: serde_json::to_value(options).ok(),
+            };
+            if let Err(e) = self.client.register_capability(vec![registration]).await {
+                (self.client)
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Uiua files will not be watched: {e}"),
+                    )
+                    .await;
+            }
+        }
     }
 
+    /// Whether positions from the client count UTF-16 code units rather than characters
+    ///
+    /// UTF-16 is the default, but UTF-32 is negotiated in `initialize` if the client supports it.
+    static UTF16_POSITIONS: AtomicBool = AtomicBool::new(true);
+
     const STACK_FUNCTION_STT: SemanticTokenType = SemanticTokenType::new("stack-function");
     const NOADIC_FUNCTION_STT: SemanticTokenType = SemanticTokenType::new("noadic-function");
     const MONADIC_FUNCTION_STT: SemanticTokenType = SemanticTokenType::new("monadic-function");
@@ -301,6 +1628,30 @@ mod server {
     #[tower_lsp::async_trait]
     impl LanguageServer for Backend {
         async fn initialize(&self, _params: InitializeParams) -> Result<InitializeResult> {
+            #[allow(deprecated)]
+            let roots: Vec<PathBuf> = match &_params.workspace_folders {
+                Some(folders) => (folders.iter())
+                    .filter_map(|folder| folder.uri.to_file_path().ok())
+                    .collect(),
+                None => (_params.root_uri.iter())
+                    .filter_map(|uri| uri.to_file_path().ok())
+                    .collect(),
+            };
+            *self.roots.lock() = roots;
+            let nested_signature_hints = (_params.initialization_options.as_ref())
+                .and_then(|options| options.get("nestedSignatureHints"))
+                .and_then(|nested| nested.as_bool())
+                .unwrap_or(false);
+            (self.nested_signature_hints).store(nested_signature_hints, Ordering::Relaxed);
+            let utf32 = (_params.capabilities.general.as_ref())
+                .and_then(|general| general.position_encodings.as_ref())
+                .is_some_and(|encodings| encodings.contains(&PositionEncodingKind::UTF32));
+            UTF16_POSITIONS.store(!utf32, Ordering::Relaxed);
+            let watch = (_params.capabilities.workspace.as_ref())
+                .and_then(|workspace| workspace.did_change_watched_files)
+                .and_then(|watched| watched.dynamic_registration)
+                .unwrap_or(false);
+            (self.watch_files).store(watch, Ordering::Relaxed);
             self.client
                 .log_message(MessageType::INFO, "Initializing Uiua language server")
                 .await;
@@ -312,11 +1663,55 @@ mod server {
                 .await;
             Ok(InitializeResult {
                 capabilities: ServerCapabilities {
+                    position_encoding: Some(if utf32 {
+                        PositionEncodingKind::UTF32
+                    } else {
+                        PositionEncodingKind::UTF16
+                    }),
                     text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                        TextDocumentSyncKind::FULL,
+                        TextDocumentSyncKind::INCREMENTAL,
                     )),
                     hover_provider: Some(HoverProviderCapability::Simple(true)),
+                    completion_provider: Some(CompletionOptions {
+                        trigger_characters: Some(vec!["&".into()]),
+                        ..Default::default()
+                    }),
+                    signature_help_provider: Some(SignatureHelpOptions {
+                        trigger_characters: Some(
+                            (Primitive::all())
+                                .filter(|prim| prim.is_modifier())
+                                .flat_map(|prim| prim.glyph())
+                                .chain(['(', ')'])
+                                .map(String::from)
+                                .collect(),
+                        ),
+                        retrigger_characters: None,
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    }),
+                    definition_provider: Some(OneOf::Left(true)),
+                    document_symbol_provider: Some(OneOf::Left(true)),
+                    inlay_hint_provider: Some(OneOf::Left(true)),
+                    references_provider: Some(OneOf::Left(true)),
+                    rename_provider: Some(OneOf::Right(RenameOptions {
+                        prepare_provider: Some(true),
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    })),
+                    code_action_provider: Some(CodeActionProviderCapability::Options(
+                        CodeActionOptions {
+                            code_action_kinds: Some(vec![
+                                CodeActionKind::REFACTOR_EXTRACT,
+                                CodeActionKind::REFACTOR_INLINE,
+                                CodeActionKind::REFACTOR_REWRITE,
+                            ]),
+                            ..Default::default()
+                        },
+                    )),
                     document_formatting_provider: Some(OneOf::Left(true)),
+                    document_range_formatting_provider: Some(OneOf::Left(true)),
+                    document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                        first_trigger_character: " ".into(),
+                        more_trigger_character: Some(vec!["\n".into()]),
+                    }),
                     semantic_tokens_provider: Some(
                         SemanticTokensServerCapabilities::SemanticTokensOptions(
                             SemanticTokensOptions {
@@ -337,23 +1732,56 @@ mod server {
         }
 
         async fn initialized(&self, _: InitializedParams) {
+            if self.watch_files.load(Ordering::Relaxed) {
+                self.register_file_watcher().await;
+            }
             self.client
                 .log_message(MessageType::INFO, "Uiua language server initialized")
                 .await;
         }
 
+        async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+            let mut lint_config_changed = false;
+            for change in params.changes {
+                let uri = canonical_uri(&change.uri);
+                if uri.path().ends_with("/.lint.ua") {
+                    lint_config_changed = true;
+                } else {
+                    self.file_index.lock().file_changed(&uri, change.typ);
+                }
+            }
+            if !lint_config_changed {
+                return;
+            }
+            self.lint_configs.lock().clear_searches();
+            let docs: Vec<_> = (self.docs.iter())
+                .map(|doc| (doc.key().clone(), doc.input.clone()))
+                .collect();
+            for (uri, input) in docs {
+                self.update_doc(uri, input, None).await;
+            }
+        }
+
         async fn did_open(&self, param: DidOpenTextDocumentParams) {
-            self.docs.insert(
-                param.text_document.uri,
-                LspDoc::new(param.text_document.text),
-            );
+            let document = param.text_document;
+            self.update_doc(document.uri, document.text, Some(document.version))
+                .await;
         }
 
         async fn did_change(&self, params: DidChangeTextDocumentParams) {
-            self.docs.insert(
-                params.text_document.uri,
-                LspDoc::new(params.content_changes[0].text.clone()),
-            );
+            let document = params.text_document;
+            let mut input = (self.docs.get(&document.uri))
+                .map(|doc| doc.input.clone())
+                .unwrap_or_default();
+            apply_content_changes(&mut input, params.content_changes);
+            self.update_doc(document.uri, input, Some(document.version))
+                .await;
+        }
+
+        async fn did_close(&self, params: DidCloseTextDocumentParams) {
+            let uri = params.text_document.uri;
+            self.docs.remove(&uri);
+            self.client.publish_diagnostics(uri, Vec::new(), None).await;
         }
 
         async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
@@ -365,7 +1793,8 @@ mod server {
             } else {
                 return Ok(None);
             };
-            let (line, col) = lsp_pos_to_uiua(params.text_document_position_params.position);
+            let position = params.text_document_position_params.position;
+            let (line, col) = lsp_pos_to_uiua(&doc.input, position);
             let mut prim_range = None;
             for sp in &doc.spans {
                 if sp.span.contains_line_col(line, col) {
@@ -442,6 +1871,377 @@ mod server {
             }))
         }
 
+        async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+            let uri = &params.text_document_position.text_document.uri;
+            let Some(doc) = self.docs.get(uri) else {
+                return Ok(None);
+            };
+            let position = params.text_document_position.position;
+            let end = lsp_pos_to_char_pos(&doc.input, position);
+            let chars: Vec<char> = doc.input.chars().take(end).collect();
+            let line_start = (chars.iter())
+                .rposition(|&c| c == '\n')
+                .map_or(0, |i| i + 1);
+            let before = &chars[line_start..];
+            let start = (before.iter())
+                .rposition(|&c| !is_ident_char(c))
+                .map_or(0, |i| i + 1);
+            let word: String = before[start..].iter().collect();
+            let sys = start > 0 && before[start - 1] == '&';
+            let range = Range::new(
+                char_pos_to_lsp(&doc.input, line_start + start - sys as usize),
+                position,
+            );
+            let edit =
+                |new_text: String| Some(CompletionTextEdit::Edit(TextEdit::new(range, new_text)));
+            let lower = word.to_lowercase();
+            let mut items = Vec::new();
+
+            // System functions
+            if sys {
+                for prim in Primitive::non_deprecated() {
+                    if matches!(prim, Primitive::Sys(_)) && prim.name()[1..].starts_with(&lower) {
+                        items.push(CompletionItem {
+                            label: prim.name().into(),
+                            kind: Some(CompletionItemKind::FUNCTION),
+                            detail: Some(prim_detail(prim)),
+                            documentation: prim_documentation(prim),
+                            text_edit: edit(prim.name().into()),
+                            ..Default::default()
+                        });
+                    }
+                }
+                return Ok(Some(CompletionResponse::Array(items)));
+            }
+            if word.is_empty() {
+                return Ok(None);
+            }
+
+            // Primitive name prefixes, which the formatter would turn into glyphs
+            if let Some(prims) = Primitive::from_format_name_multi(&word) {
+                let glyphs: String = (prims.iter()).map(|(prim, _)| prim.to_string()).collect();
+                let names: Vec<&str> = prims.iter().map(|(prim, _)| prim.name()).collect();
+                items.push(CompletionItem {
+                    label: glyphs.clone(),
+                    kind: Some(CompletionItemKind::OPERATOR),
+                    detail: Some(names.join(" ")),
+                    filter_text: Some(word.clone()),
+                    sort_text: Some("0".into()),
+                    preselect: Some(true),
+                    text_edit: edit(glyphs),
+                    ..Default::default()
+                });
+            }
+            for prim in Primitive::non_deprecated() {
+                if matches!(prim, Primitive::Sys(_)) || !prim.name().starts_with(&lower) {
+                    continue;
+                }
+                items.push(CompletionItem {
+                    label: prim.name().into(),
+                    kind: Some(CompletionItemKind::FUNCTION),
+                    detail: Some(prim_detail(prim)),
+                    documentation: prim_documentation(prim),
+                    sort_text: Some(format!("1{}", prim.name())),
+                    text_edit: edit(prim.to_string()),
+                    ..Default::default()
+                });
+            }
+
+            // Bindings
+            let mut workspace = self.workspace();
+            let canonical = canonical_uri(uri);
+            let mut seen = HashSet::new();
+            for (ident, info) in &doc.bindings {
+                if !ident.value.to_lowercase().starts_with(&lower)
+                    || !seen.insert(ident.value.clone())
+                {
+                    continue;
+                }
+                let (_, source) = workspace.resolve(&canonical, &ident.value, info, false);
+                let mut detail = source.signature.map(|sig| sig.to_string());
+                if let Some((import_path, _)) = &info.import {
+                    let from = format!("from {import_path}");
+                    detail = Some(detail.map_or(from.clone(), |sig| format!("{sig} {from}")));
+                }
+                items.push(CompletionItem {
+                    label: ident.value.to_string(),
+                    kind: Some(CompletionItemKind::VARIABLE),
+                    detail,
+                    documentation: (source.comment.clone()).map(Documentation::String),
+                    sort_text: Some(format!("2{}", ident.value)),
+                    text_edit: edit(ident.value.to_string()),
+                    ..Default::default()
+                });
+            }
+            Ok(Some(CompletionResponse::Array(items)))
+        }
+
+        async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+            let Some(doc) = self.docs.get(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            let nested = self.nested_signature_hints.load(Ordering::Relaxed);
+            let mut hints = Vec::new();
+            let mut positions = HashSet::new();
+            for hint in &doc.check.signatures {
+                let top_level = matches!(
+                    hint.kind,
+                    SignatureHintKind::Binding | SignatureHintKind::Line
+                );
+                if !top_level && !nested {
+                    continue;
+                }
+                let position = uiua_loc_to_lsp(&hint.span.input, hint.span.end);
+                if position < params.range.start || position > params.range.end {
+                    continue;
+                }
+                // A function that makes up a whole binding or line only gets one hint
+                if !positions.insert((position.line, position.character)) {
+                    continue;
+                }
+                hints.push(InlayHint {
+                    position,
+                    label: InlayHintLabel::String(hint.signature.to_string()),
+                    kind: Some(InlayHintKind::TYPE),
+                    text_edits: None,
+                    tooltip: None,
+                    padding_left: Some(true),
+                    padding_right: None,
+                    data: None,
+                });
+            }
+            Ok(Some(hints))
+        }
+
+        async fn goto_definition(
+            &self,
+            params: GotoDefinitionParams,
+        ) -> Result<Option<GotoDefinitionResponse>> {
+            let params = params.text_document_position_params;
+            let mut workspace = self.workspace();
+            let uri = canonical_uri(&params.text_document.uri);
+            let Some((ident, info)) = workspace.binding_at(&uri, params.position) else {
+                return Ok(None);
+            };
+            let (uri, info) = workspace.resolve(&uri, &ident.value, &info, false);
+            Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
+                workspace.client_uri(&uri),
+                uiua_span_to_lsp(&info.span),
+            ))))
+        }
+
+        async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+            let position = params.text_document_position;
+            let mut workspace = self.workspace();
+            let uri = canonical_uri(&position.text_document.uri);
+            let Some((ident, info)) = workspace.binding_at(&uri, position.position) else {
+                return Ok(None);
+            };
+            let (target_uri, target) = workspace.resolve(&uri, &ident.value, &info, false);
+            let mut locations = Vec::new();
+            for (uri, ident, info) in workspace.references(&target_uri, &target, false) {
+                let declaration =
+                    uri == target_uri && Arc::ptr_eq(&info, &target) && ident.span == info.span;
+                if declaration && !params.context.include_declaration {
+                    continue;
+                }
+                locations.push(Location::new(
+                    workspace.client_uri(&uri),
+                    uiua_span_to_lsp(&ident.span),
+                ));
+            }
+            Ok(Some(locations))
+        }
+
+        async fn prepare_rename(
+            &self,
+            params: TextDocumentPositionParams,
+        ) -> Result<Option<PrepareRenameResponse>> {
+            let mut workspace = self.workspace();
+            let uri = canonical_uri(&params.text_document.uri);
+            Ok(workspace
+                .binding_at(&uri, params.position)
+                .map(|(ident, _)| PrepareRenameResponse::Range(uiua_span_to_lsp(&ident.span))))
+        }
+
+        async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+            let position = params.text_document_position;
+            let mut workspace = self.workspace();
+            let uri = canonical_uri(&position.text_document.uri);
+            let Some((ident, info)) = workspace.binding_at(&uri, position.position) else {
+                return Ok(None);
+            };
+            let new_name = params.new_name;
+            if let Some(problem) = rename_problem(&ident.value, &new_name) {
+                return Err(jsonrpc::Error::invalid_params(problem));
+            }
+            // Items imported under a different name keep that name
+            let (target_uri, target) = workspace.resolve(&uri, &ident.value, &info, true);
+            let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+            let mut renamed_in = HashSet::new();
+            for (uri, ident, _) in workspace.references(&target_uri, &target, true) {
+                (changes.entry(workspace.client_uri(&uri)).or_default()).push(TextEdit::new(
+                    uiua_span_to_lsp(&ident.span),
+                    new_name.clone(),
+                ));
+                renamed_in.insert(uri);
+            }
+            for (uri, span) in workspace.import_references(&target_uri, &target) {
+                // Only replace the inside of the string
+                let mut range = uiua_span_to_lsp(&span);
+                range.start.character += 1;
+                range.end.character = range.end.character.saturating_sub(1);
+                (changes.entry(workspace.client_uri(&uri)).or_default())
+                    .push(TextEdit::new(range, new_name.clone()));
+            }
+            // The new name must not collide with a binding in any file where it will be used
+            if new_name != ident.value.as_ref() {
+                for uri in renamed_in {
+                    let Some(bindings) = workspace.bindings(&uri) else {
+                        continue;
+                    };
+                    let collision = bindings.iter().find(|(ident, info)| {
+                        ident.value.as_ref() == new_name && info.span == ident.span
+                    });
+                    if let Some((ident, _)) = collision {
+                        let file = (uri.path_segments())
+                            .and_then(|mut segments| segments.next_back())
+                            .unwrap_or_default();
+                        return Err(jsonrpc::Error::invalid_params(format!(
+                            "`{new_name}` is already bound at {file}:{}",
+                            ident.span.start
+                        )));
+                    }
+                }
+            }
+            Ok(Some(WorkspaceEdit::new(changes)))
+        }
+
+        async fn code_action(
+            &self,
+            params: CodeActionParams,
+        ) -> Result<Option<CodeActionResponse>> {
+            let uri = params.text_document.uri;
+            // Built before the document is borrowed so the map is not locked twice
+            let mut workspace = self.workspace();
+            let Some(doc) = self.docs.get(&uri) else {
+                return Ok(None);
+            };
+            let range = params.range;
+            let mut actions = Vec::new();
+            if range.start != range.end {
+                if let Ok(Some(edit)) = format_range_edit(&doc, range) {
+                    actions.push(code_action(
+                        "Format names as glyphs",
+                        CodeActionKind::REFACTOR_REWRITE,
+                        &uri,
+                        vec![edit],
+                    ));
+                }
+                let edits = asciify_edits(&doc, range);
+                if !edits.is_empty() {
+                    actions.push(code_action(
+                        "Replace glyphs with names",
+                        CodeActionKind::REFACTOR_REWRITE,
+                        &uri,
+                        edits,
+                    ));
+                }
+                if let Some((name, edits)) = extract_binding_edits(&doc, range) {
+                    actions.push(code_action(
+                        format!("Extract into binding {name}"),
+                        CodeActionKind::REFACTOR_EXTRACT,
+                        &uri,
+                        edits,
+                    ));
+                }
+            }
+            let canonical = canonical_uri(&uri);
+            if let Some((name, edits)) =
+                inline_binding_edits(&doc, &canonical, range.start, &mut workspace)
+            {
+                actions.push(code_action(
+                    format!("Inline binding {name}"),
+                    CodeActionKind::REFACTOR_INLINE,
+                    &uri,
+                    edits,
+                ));
+            }
+            Ok(Some(actions))
+        }
+
+        async fn signature_help(
+            &self,
+            params: SignatureHelpParams,
+        ) -> Result<Option<SignatureHelp>> {
+            let position = params.text_document_position_params;
+            let Some(doc) = self.docs.get(&position.text_document.uri) else {
+                return Ok(None);
+            };
+            let (line, col) = lsp_pos_to_uiua(&doc.input, position.position);
+            let Some(m) = items_modified_at(&doc.items, line, col) else {
+                return Ok(None);
+            };
+            let expectations = operand_expectations(&m.modifier.value);
+            if expectations.is_empty() {
+                return Ok(None);
+            }
+            // A switch function fills all the operands with its branches
+            let operand_spans: Vec<CodeSpan> = match m.code_operands().next() {
+                Some(Sp {
+                    value: Word::Switch(sw),
+                    ..
+                }) if sw.branches.len() >= expectations.len() => sw
+                    .branches
+                    .iter()
+                    .map(|branch| branch.span.clone())
+                    .collect(),
+                _ => m.code_operands().map(|word| word.span.clone()).collect(),
+            };
+            let active = (operand_spans.iter())
+                .position(|span| (line, col) <= (span.end.line, span.end.col))
+                .unwrap_or(operand_spans.len())
+                .min(expectations.len() - 1);
+            let mut label = m.modifier.value.to_string();
+            let mut parameters = Vec::new();
+            for (name, expected) in expectations {
+                label.push(' ');
+                let start = label.chars().count() as u32;
+                label.push_str(name);
+                let end = label.chars().count() as u32;
+                parameters.push(ParameterInformation {
+                    label: ParameterLabel::LabelOffsets([start, end]),
+                    documentation: Some(Documentation::String(format!("{name}: {expected}"))),
+                });
+            }
+            let documentation = match m.modifier.value {
+                Modifier::Primitive(prim) => prim_documentation(prim),
+                Modifier::Ident(_) => None,
+            };
+            Ok(Some(SignatureHelp {
+                signatures: vec![SignatureInformation {
+                    label,
+                    documentation,
+                    parameters: Some(parameters),
+                    active_parameter: Some(active as u32),
+                }],
+                active_signature: Some(0),
+                active_parameter: Some(active as u32),
+            }))
+        }
+
+        async fn document_symbol(
+            &self,
+            params: DocumentSymbolParams,
+        ) -> Result<Option<DocumentSymbolResponse>> {
+            let Some(doc) = self.docs.get(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            Ok(Some(DocumentSymbolResponse::Nested(item_symbols(
+                &doc.items, &doc.check,
+            ))))
+        }
+
         async fn formatting(
             &self,
             params: DocumentFormattingParams,
@@ -463,6 +2263,38 @@ mod server {
             }]))
         }
 
+        async fn range_formatting(
+            &self,
+            params: DocumentRangeFormattingParams,
+        ) -> Result<Option<Vec<TextEdit>>> {
+            let Some(doc) = self.docs.get(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            Ok(match format_range_edit(&doc, params.range) {
+                Ok(edit) => Some(edit.into_iter().collect()),
+                Err(_) => None,
+            })
+        }
+
+        async fn on_type_formatting(
+            &self,
+            params: DocumentOnTypeFormattingParams,
+        ) -> Result<Option<Vec<TextEdit>>> {
+            let position = params.text_document_position;
+            let Some(doc) = self.docs.get(&position.text_document.uri) else {
+                return Ok(None);
+            };
+            // Only the name right before the typed space or newline is complete,
+            // so names that are still being typed are left alone
+            let end = lsp_pos_to_char_pos(&doc.input, position.position);
+            let before: Vec<char> = doc.input.chars().take(end).collect();
+            let whitespace = (before.iter().rev())
+                .take_while(|c| c.is_whitespace())
+                .count();
+            let edit = name_glyphs_edit(&doc, before.len() - whitespace);
+            Ok(Some(edit.into_iter().collect()))
+        }
+
         async fn inline_value(
             &self,
             params: InlineValueParams,
@@ -536,7 +2368,7 @@ mod server {
                     .position(|t| t == &token_type)
                     .unwrap() as u32;
                 let span = &sp.span;
-                let start = uiua_loc_to_lsp(span.start);
+                let start = uiua_loc_to_lsp(&span.input, span.start);
                 let delta_start = if start.character > prev_char {
                     start.character - prev_char
                 } else {
@@ -545,7 +2377,7 @@ mod server {
                 tokens.push(SemanticToken {
                     delta_line: start.line - prev_line,
                     delta_start,
-                    length: (span.end.char_pos - span.start.char_pos) as u32,
+                    length: lsp_len(span.as_str()) as u32,
                     token_type,
                     token_modifiers_bitset: 0,
                 });
@@ -563,19 +2395,311 @@ mod server {
         }
     }
 
-    fn lsp_pos_to_uiua(pos: Position) -> (usize, usize) {
-        (pos.line as usize + 1, pos.character as usize + 1)
+    fn prim_detail(prim: Primitive) -> String {
+        let mut detail = String::new();
+        if let Some(glyph) = prim.glyph() {
+            detail.push(glyph);
+            detail.push(' ');
+        }
+        if let Some(margs) = prim.modifier_args() {
+            detail.push_str(&format!("{margs}-function modifier"));
+        } else if let (Some(args), Some(outputs)) = (prim.args(), prim.outputs()) {
+            let sig = Signature::new(args as usize, outputs as usize);
+            detail.push_str(&sig.to_string());
+        }
+        detail.trim_end().into()
+    }
+
+    fn prim_documentation(prim: Primitive) -> Option<Documentation> {
+        let doc = prim.doc()?;
+        Some(Documentation::String(doc.short_text().into_owned()))
+    }
+
+    /// Get the length of some text in the negotiated position encoding
+    fn lsp_len(s: &str) -> usize {
+        if UTF16_POSITIONS.load(Ordering::Relaxed) {
+            s.encode_utf16().count()
+        } else {
+            s.chars().count()
+        }
+    }
+
+    /// Get the character column of an LSP character offset in a line
+    fn lsp_character_to_col(line: &str, character: u32) -> usize {
+        if !UTF16_POSITIONS.load(Ordering::Relaxed) {
+            return character as usize;
+        }
+        let mut units = 0;
+        let chars = (line.chars())
+            .take_while(|c| {
+                units += c.len_utf16();
+                units <= character as usize
+            })
+            .count();
+        // Positions past the end of the line are kept past the end
+        chars + (character as usize).saturating_sub(line.encode_utf16().count())
+    }
+
+    fn lsp_pos_to_uiua(input: &str, pos: Position) -> (usize, usize) {
+        let line = input.split('\n').nth(pos.line as usize).unwrap_or_default();
+        let col = lsp_character_to_col(line, pos.character);
+        (pos.line as usize + 1, col + 1)
+    }
+
+    fn lsp_pos_to_char_pos(input: &str, pos: Position) -> usize {
+        let mut lines = input.split('\n');
+        let line_start: usize = (lines.by_ref())
+            .take(pos.line as usize)
+            .map(|line| line.chars().count() + 1)
+            .sum();
+        let line = lines.next().unwrap_or_default();
+        line_start + lsp_character_to_col(line, pos.character)
+    }
+
+    /// Apply the changes from a `didChange` notification to a document's text
+    fn apply_content_changes(input: &mut String, changes: Vec<TextDocumentContentChangeEvent>) {
+        for change in changes {
+            let Some(range) = change.range else {
+                *input = change.text;
+                continue;
+            };
+            let start = char_pos_to_byte_pos(input, lsp_pos_to_char_pos(input, range.start));
+            let end = char_pos_to_byte_pos(input, lsp_pos_to_char_pos(input, range.end));
+            input.replace_range(start..end.max(start), &change.text);
+        }
+    }
+
+    fn char_pos_to_byte_pos(input: &str, pos: usize) -> usize {
+        (input.char_indices().nth(pos)).map_or(input.len(), |(i, _)| i)
     }
 
-    fn uiua_loc_to_lsp(loc: Loc) -> Position {
-        Position::new(loc.line as u32 - 1, loc.col as u32 - 1)
+    fn char_pos_to_lsp(input: &str, pos: usize) -> Position {
+        let before: Vec<char> = input.chars().take(pos).collect();
+        let line = before.iter().filter(|&&c| c == '\n').count();
+        let line_before: String = (before.iter().rev()).take_while(|&&c| c != '\n').collect();
+        Position::new(line as u32, lsp_len(&line_before) as u32)
     }
 
-    fn uiua_locs_to_lsp(start: Loc, end: Loc) -> Range {
-        Range::new(uiua_loc_to_lsp(start), uiua_loc_to_lsp(end))
+    fn char_pos_to_uiua(input: &str, pos: usize) -> (usize, usize) {
+        let before: Vec<char> = input.chars().take(pos).collect();
+        let line = before.iter().filter(|&&c| c == '\n').count();
+        let col = before.iter().rev().take_while(|&&c| c != '\n').count();
+        (line + 1, col + 1)
+    }
+
+    fn uiua_loc_to_lsp(input: &str, loc: Loc) -> Position {
+        let line_start = input[..loc.byte_pos].rfind('\n').map_or(0, |i| i + 1);
+        let character = lsp_len(&input[line_start..loc.byte_pos]);
+        Position::new(loc.line as u32 - 1, character as u32)
     }
 
     fn uiua_span_to_lsp(span: &CodeSpan) -> Range {
-        uiua_locs_to_lsp(span.start, span.end)
+        Range::new(
+            uiua_loc_to_lsp(&span.input, span.start),
+            uiua_loc_to_lsp(&span.input, span.end),
+        )
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        /// Get the lines of the identifiers that refer to the binding defined on a line
+        fn reference_lines(bindings: &BindingsInfo, name: &str, def_line: usize) -> Vec<usize> {
+            let (_, def) = (bindings.iter())
+                .find(|(ident, info)| {
+                    &*ident.value == name
+                        && info.span == ident.span
+                        && ident.span.start.line == def_line
+                })
+                .unwrap();
+            (bindings.iter())
+                .filter(|(_, info)| Arc::ptr_eq(info, def))
+                .map(|(ident, _)| ident.span.start.line)
+                .collect()
+        }
+
+        /// Apply edits that do not overlap to some code
+        fn apply_edits(code: &str, mut edits: Vec<TextEdit>) -> String {
+            edits.sort_by_key(|edit| edit.range.start);
+            let mut chars: Vec<char> = code.chars().collect();
+            for edit in edits.into_iter().rev() {
+                let start = lsp_pos_to_char_pos(code, edit.range.start);
+                let end = lsp_pos_to_char_pos(code, edit.range.end);
+                chars.splice(start..end, edit.new_text.chars());
+            }
+            chars.into_iter().collect()
+        }
+
+        fn open_doc(code: &str) -> (DashMap<Url, LspDoc>, Url) {
+            let uri = Url::parse("untitled:test.ua").unwrap();
+            let lint_configs = Mutex::new(LintConfigCache::default());
+            let docs = DashMap::new();
+            docs.insert(uri.clone(), LspDoc::new(code.into(), &uri, &lint_configs));
+            (docs, uri)
+        }
+
+        #[test]
+        fn inline_binding() {
+            let code = "F ← +1\nF 1\n---\nF 2\n---\n";
+            let (docs, uri) = open_doc(code);
+            let index = Mutex::new(FileIndex::default());
+            let mut workspace = Workspace::new(&docs, &[], &index);
+            let doc = docs.get(&uri).unwrap();
+            let (name, edits) =
+                inline_binding_edits(&doc, &uri, Position::new(0, 0), &mut workspace).unwrap();
+            assert_eq!(&*name, "F");
+            assert_eq!(apply_edits(code, edits), "+1 1\n---\n+1 2\n---\n");
+        }
+
+        #[test]
+        fn extract_binding() {
+            let code = "+1 2\n";
+            let (docs, uri) = open_doc(code);
+            let doc = docs.get(&uri).unwrap();
+            let range = Range::new(Position::new(0, 0), Position::new(0, 2));
+            let (name, edits) = extract_binding_edits(&doc, range).unwrap();
+            assert_eq!(&*name, "Extracted");
+            assert_eq!(
+                apply_edits(code, edits),
+                "Extracted ← |1.1 +1\nExtracted 2\n"
+            );
+
+            // In a test scope, the binding goes in the scope
+            // and the scope's bindings are used for the signature
+            let code = "F ← +1\n---\nG ← 5\nF G\n---\n";
+            let (docs, uri) = open_doc(code);
+            let doc = docs.get(&uri).unwrap();
+            let range = Range::new(Position::new(3, 0), Position::new(3, 3));
+            let (_, edits) = extract_binding_edits(&doc, range).unwrap();
+            assert_eq!(
+                apply_edits(code, edits),
+                "F ← +1\n---\nG ← 5\nExtracted ← |0.1 F G\nExtracted\n---\n"
+            );
+        }
+
+        #[test]
+        fn utf16_positions() {
+            // `𝄞` takes two UTF-16 code units
+            let code = "F ← 1\n\"𝄞\" F\n";
+            let (items, _, _) = parse(code, None);
+            let bindings = bindings_info(&items);
+            let (ident, _) = (bindings.iter())
+                .find(|(ident, _)| ident.span.start.line == 2)
+                .unwrap();
+            let range = uiua_span_to_lsp(&ident.span);
+            assert_eq!(range, Range::new(Position::new(1, 5), Position::new(1, 6)));
+            assert_eq!(lsp_pos_to_uiua(code, Position::new(1, 5)), (2, 5));
+            assert_eq!(lsp_pos_to_char_pos(code, Position::new(1, 5)), 10);
+            assert_eq!(char_pos_to_lsp(code, 10), Position::new(1, 5));
+        }
+
+        #[test]
+        fn incremental_changes() {
+            let change =
+                |start: (u32, u32), end: (u32, u32), text: &str| TextDocumentContentChangeEvent {
+                    range: Some(Range::new(
+                        Position::new(start.0, start.1),
+                        Position::new(end.0, end.1),
+                    )),
+                    range_length: None,
+                    text: text.into(),
+                };
+            let mut input = String::from("a ← \"𝄞é\"\nb ← 1\n");
+            apply_content_changes(
+                &mut input,
+                vec![
+                    change((0, 5), (0, 7), "ü"),
+                    change((0, 7), (0, 7), "𝄞"),
+                    change((1, 4), (1, 5), "\"𝄞\""),
+                    change((1, 7), (1, 7), "!"),
+                ],
+            );
+            assert_eq!(input, "a ← \"üé𝄞\"\nb ← \"𝄞!\"\n");
+        }
+
+        #[test]
+        fn test_scope_bindings() {
+            let code = "F ← +1\nF 1\n---\nF 2\nG ← F\nG 3\n---\nG ← 5\nG\n";
+            let (items, _, _) = parse(code, None);
+            let bindings = bindings_info(&items);
+            assert_eq!(reference_lines(&bindings, "F", 1), [1, 2, 4, 5]);
+            assert_eq!(reference_lines(&bindings, "G", 5), [5, 6]);
+            assert_eq!(reference_lines(&bindings, "G", 8), [8, 9]);
+        }
+
+        #[test]
+        fn rename_problems() {
+            assert_eq!(rename_problem("F", "Foo"), None);
+            assert_eq!(rename_problem("F!", "Foo!"), None);
+            assert!(rename_problem("F", "add").unwrap().contains("primitives"));
+            assert!(rename_problem("F", "A B").unwrap().contains("not a valid"));
+            assert!(rename_problem("F", "X1").unwrap().contains("not a valid"));
+            assert!(rename_problem("F", "Foo!")
+                .unwrap()
+                .contains("modifier arguments"));
+            assert!(rename_problem("F", "foo").unwrap().contains("TitleCase"));
+        }
+
+        #[test]
+        fn workspace_references() {
+            let dir = std::env::temp_dir().join(format!("uiua-lsp-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let dir = fs::canonicalize(dir).unwrap();
+            let a = dir.join("a.ua");
+            let b = dir.join("b.ua");
+            let a_code = "F ← +1\nF 1\n---\nF 2\n---\n";
+            fs::write(&a, a_code).unwrap();
+            fs::write(&b, "G ← &i \"a.ua\" \"F\"\nG 3\n").unwrap();
+            let a_uri = Url::from_file_path(&a).unwrap();
+            let b_uri = Url::from_file_path(&b).unwrap();
+
+            // Only `a.ua` is open
+            let docs = DashMap::new();
+            let lint_configs = Mutex::new(LintConfigCache::default());
+            let doc = LspDoc::new(a_code.into(), &a_uri, &lint_configs);
+            docs.insert(a_uri.clone(), doc);
+            let index = Mutex::new(FileIndex::default());
+            let mut workspace = Workspace::new(&docs, std::slice::from_ref(&dir), &index);
+
+            let (ident, info) = workspace.binding_at(&a_uri, Position::new(3, 0)).unwrap();
+            let (target_uri, target) = workspace.resolve(&a_uri, &ident.value, &info, false);
+            assert_eq!(target_uri, a_uri);
+            let mut references: Vec<(String, usize)> =
+                (workspace.references(&target_uri, &target, false))
+                    .into_iter()
+                    .map(|(uri, ident, _)| {
+                        (
+                            uri.path().rsplit('/').next().unwrap().into(),
+                            ident.span.start.line,
+                        )
+                    })
+                    .collect();
+            references.sort();
+            let expected = [
+                ("a.ua", 1),
+                ("a.ua", 2),
+                ("a.ua", 4),
+                ("b.ua", 1),
+                ("b.ua", 2),
+            ];
+            assert_eq!(references, expected.map(|(file, line)| (file.into(), line)));
+
+            // Renaming keeps the import's own name
+            let same_name: Vec<Url> = (workspace.references(&target_uri, &target, true))
+                .into_iter()
+                .map(|(uri, _, _)| uri)
+                .collect();
+            assert!(!same_name.contains(&b_uri));
+            let imports = workspace.import_references(&target_uri, &target);
+            assert_eq!(imports.len(), 1);
+            assert_eq!(imports[0].0, b_uri);
+
+            // A binding that another file imports is not inlined
+            let doc = docs.get(&a_uri).unwrap();
+            let inlined = inline_binding_edits(&doc, &a_uri, Position::new(0, 0), &mut workspace);
+            assert!(inlined.is_none());
+            _ = fs::remove_dir_all(&dir);
+        }
     }
 }
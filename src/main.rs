@@ -2,8 +2,11 @@
 compile_error!("To compile the uiua interpreter binary, you must enable the `binary` feature flag");
 
 use std::{
+    any::Any,
+    borrow::Cow,
     env, fmt, fs,
     io::{self, stderr, Write},
+    mem::take,
     path::{Path, PathBuf},
     process::{exit, Child, Command, Stdio},
     sync::mpsc::channel,
@@ -13,14 +16,27 @@ use std::{
 
 use clap::{error::ErrorKind, Parser};
 use colored::*;
+use image::DynamicImage;
 use instant::Instant;
 use notify::{EventKind, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
-use rustyline::{error::ReadlineError, DefaultEditor};
+use rustyline::{
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::FileHistory,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Editor, Helper,
+};
 use uiua::{
-    format::{format_file, format_str, FormatConfig, FormatConfigSource},
-    spans, PrimClass, RunMode, SpanKind, Uiua, UiuaError, UiuaResult, Value,
+    check_code_static,
+    format::{format, format_file, format_str, FormatConfig, FormatConfigSource},
+    is_ident_char, spans, AudioStreamFn, Handle, InterruptHandle, NativeSys, PolicySys, PrimClass,
+    PrimDocFragment, PrimDocLine, Primitive, RecordSys, ReplaySys, Report, RunMode, Signature,
+    SignatureHintKind, SpanKind, SysBackend, SysPolicy, SysTranscript, TestCase, TestFailure,
+    TestOptions, Uiua, UiuaError, UiuaResult, Value,
 };
 
 fn main() {
@@ -33,6 +49,8 @@ fn main() {
             *child = None;
             println!("# Program interrupted");
             print_watching();
+        } else if let Some(handle) = &*REPL_INTERRUPT.lock() {
+            handle.interrupt();
         } else {
             if let Ok(App::Watch { .. }) | Err(_) = App::try_parse() {
                 clear_watching_with(" ", "");
@@ -48,6 +66,8 @@ fn main() {
 }
 
 static WATCH_CHILD: Lazy<Mutex<Option<Child>>> = Lazy::new(Default::default);
+/// Interrupts the REPL's current evaluation
+static REPL_INTERRUPT: Lazy<Mutex<Option<InterruptHandle>>> = Lazy::new(Default::default);
 
 fn run() -> UiuaResult {
     if cfg!(feature = "profile") {
@@ -76,13 +96,51 @@ fn run() -> UiuaResult {
             App::Fmt {
                 path,
                 formatter_options,
+                check,
+                diff,
             } => {
                 let config = FormatConfig::from_source(
                     formatter_options.format_config_source,
                     path.as_deref(),
                 )?;
 
-                if let Some(path) = path {
+                if check || diff {
+                    let dir = path.unwrap_or_else(|| ".".into());
+                    let paths = if dir.is_dir() {
+                        (uiua_files_in(&dir).into_iter())
+                            .map(|path| path.strip_prefix(".").map_or(path.clone(), Into::into))
+                            .collect()
+                    } else {
+                        vec![dir]
+                    };
+                    let mut unformatted = 0;
+                    for path in paths {
+                        let input = fs::read_to_string(&path)
+                            .map_err(|e| UiuaError::Load(path.clone(), e.into()));
+                        let output = input.and_then(|input| {
+                            let output = format(&input, &path, &config)?.output;
+                            Ok((input, output))
+                        });
+                        match output {
+                            Ok((input, output)) if input == output => {}
+                            Ok((input, output)) => {
+                                unformatted += 1;
+                                if diff {
+                                    print!("{}", unified_diff(&path, &input, &output));
+                                } else {
+                                    println!("{}", path.display());
+                                }
+                            }
+                            Err(e) => {
+                                unformatted += 1;
+                                eprintln!("{}", e.report());
+                            }
+                        }
+                    }
+                    if unformatted > 0 {
+                        exit(1);
+                    }
+                } else if let Some(path) = path {
                     format_single_file(path, &config, formatter_options.stdout)?;
                 } else {
                     format_multi_files(&config, formatter_options.stdout)?;
@@ -95,6 +153,10 @@ fn run() -> UiuaResult {
                 formatter_options,
                 time_instrs,
                 mode,
+                policy_options,
+                record,
+                replay,
+                message_format,
                 #[cfg(feature = "audio")]
                 audio_options,
                 args,
@@ -114,19 +176,63 @@ fn run() -> UiuaResult {
                     let config = FormatConfig::from_source(
                         formatter_options.format_config_source,
                         Some(&path),
-                    )?;
-                    format_file(&path, &config)?;
+                    );
+                    message_format.unwrap_or_exit(config.and_then(|config| {
+                        format_file(&path, &config)?;
+                        Ok(())
+                    }));
                 }
                 let mode = mode.unwrap_or(RunMode::Normal);
                 #[cfg(feature = "audio")]
                 setup_audio(audio_options);
-                let mut rt = Uiua::with_native_sys()
+                let policy = policy_options.policy();
+                let rt = if let Some(replay) = &replay {
+                    let json = fs::read_to_string(replay)
+                        .map_err(|e| UiuaError::Load(replay.clone(), e.into()))?;
+                    let transcript = SysTranscript::from_json(&json).unwrap_or_else(|e| {
+                        eprintln!("{e}");
+                        exit(1)
+                    });
+                    Uiua::with_backend(ReplaySys::new(transcript).with_echo(true))
+                } else if record.is_some() {
+                    let policy = policy.unwrap_or_else(SysPolicy::allow_all);
+                    Uiua::with_backend(RecordSys::new(PolicySys::new(NativeSys, policy)))
+                } else {
+                    match policy {
+                        Some(policy) => Uiua::with_backend(PolicySys::new(NativeSys, policy)),
+                        None => Uiua::with_native_sys(),
+                    }
+                };
+                let mut rt = rt
                     .with_mode(mode)
                     .with_file_path(&path)
                     .with_args(args)
-                    .print_diagnostics(true)
+                    .print_diagnostics(message_format == MessageFormat::Human)
                     .time_instrs(time_instrs);
-                rt.load_file(path)?;
+                let res = rt.load_file(path);
+                message_format.print_diagnostics(&mut rt);
+                if let Some(record) = &record {
+                    let backend = rt
+                        .downcast_backend::<RecordSys<PolicySys<NativeSys>>>()
+                        .unwrap();
+                    fs::write(record, backend.transcript().to_json())
+                        .map_err(|e| UiuaError::Load(record.clone(), e.into()))?;
+                }
+                message_format.unwrap_or_exit(res);
+                if let Some(backend) = rt.downcast_backend::<ReplaySys>() {
+                    if let Some(divergence) = backend.divergence() {
+                        eprintln!("{divergence}");
+                        exit(1);
+                    }
+                    let remaining = backend.remaining();
+                    if remaining > 0 {
+                        eprintln!(
+                            "{}",
+                            format!("Warning: {remaining} recorded calls were not replayed")
+                                .yellow()
+                        );
+                    }
+                }
                 print_stack(&rt.take_stack(), !no_color);
             }
             App::Eval {
@@ -148,6 +254,10 @@ fn run() -> UiuaResult {
             App::Test {
                 path,
                 formatter_options,
+                filter,
+                per_line,
+                format,
+                message_format,
             } => {
                 let path = if let Some(path) = path {
                     path
@@ -160,14 +270,136 @@ fn run() -> UiuaResult {
                         }
                     }
                 };
-                let config =
-                    FormatConfig::from_source(formatter_options.format_config_source, Some(&path))?;
-                format_file(&path, &config)?;
-                Uiua::with_native_sys()
-                    .with_mode(RunMode::Test)
-                    .print_diagnostics(true)
-                    .load_file(path)?;
-                println!("No failures!");
+                let paths = if path.is_dir() {
+                    uiua_files_in(&path)
+                } else {
+                    vec![path]
+                };
+                let options = TestOptions { filter, per_line };
+                let human = format == TestFormat::Human;
+                let start = Instant::now();
+                let mut files = Vec::new();
+                for path in paths {
+                    let config = FormatConfig::from_source(
+                        formatter_options.format_config_source.clone(),
+                        Some(&path),
+                    );
+                    let rt = if human {
+                        Uiua::with_native_sys()
+                    } else {
+                        Uiua::with_backend(CaptureSys::default())
+                    };
+                    let mut rt = rt
+                        .with_file_path(&path)
+                        .print_diagnostics(human && message_format == MessageFormat::Human);
+                    let res = config
+                        .and_then(|config| format_file(&path, &config))
+                        .and_then(|_| rt.run_test_file(&path, &options));
+                    if human {
+                        message_format.print_diagnostics(&mut rt);
+                        print_test_file(&path, &res, message_format);
+                    }
+                    let output = (rt.downcast_backend::<CaptureSys>())
+                        .map(|sys| take(&mut *sys.stdout.lock()))
+                        .unwrap_or_default();
+                    files.push((path, res, output));
+                }
+                let elapsed = start.elapsed().as_secs_f64();
+                let failed = (files.iter())
+                    .map(|(_, res, _)| match res {
+                        Ok(cases) => cases.iter().filter(|case| !case.passed()).count(),
+                        Err(_) => 1,
+                    })
+                    .sum::<usize>();
+                match format {
+                    TestFormat::Human => {
+                        let passed = (files.iter())
+                            .filter_map(|(_, res, _)| res.as_ref().ok())
+                            .flatten()
+                            .filter(|case| case.passed())
+                            .count();
+                        let summary = format!("{passed} passed, {failed} failed");
+                        let summary = if failed == 0 {
+                            summary.green()
+                        } else {
+                            summary.red()
+                        };
+                        let elapsed = format!("({:.2}ms)", elapsed * 1000.0);
+                        println!("\n{summary} {}", elapsed.bright_black());
+                    }
+                    TestFormat::Junit => print!("{}", junit_test_report(&files, elapsed)),
+                    TestFormat::Tap => print!("{}", tap_test_report(&files)),
+                    TestFormat::Json => print!("{}", json_test_report(&files, elapsed)),
+                }
+                if failed > 0 {
+                    exit(1);
+                }
+            }
+            App::Check {
+                paths,
+                signatures,
+                message_format,
+            } => {
+                let paths = if paths.is_empty() {
+                    match working_file_path() {
+                        Ok(path) => vec![path],
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return Ok(());
+                        }
+                    }
+                } else {
+                    (paths.into_iter())
+                        .flat_map(|path| {
+                            if path.is_dir() {
+                                uiua_files_in(&path)
+                            } else {
+                                vec![path]
+                            }
+                        })
+                        .collect()
+                };
+                let mut failed = false;
+                for path in paths {
+                    let input = match fs::read_to_string(&path) {
+                        Ok(input) => input,
+                        Err(e) => {
+                            message_format.print(UiuaError::Load(path, e.into()).report());
+                            failed = true;
+                            continue;
+                        }
+                    };
+                    let check = check_code_static(&input, Some(&path));
+                    if signatures {
+                        for hint in &check.signatures {
+                            if !matches!(
+                                hint.kind,
+                                SignatureHintKind::Binding | SignatureHintKind::Line
+                            ) {
+                                continue;
+                            }
+                            let code = hint.span.as_str();
+                            let first_line = code.lines().next().unwrap_or_default();
+                            let ellipsis = if code.contains('\n') { " …" } else { "" };
+                            println!(
+                                "{}:{}: {:<6} {first_line}{ellipsis}",
+                                path.display(),
+                                hint.span.start.line,
+                                hint.signature.to_string()
+                            );
+                        }
+                    }
+                    for diagnostic in &check.diagnostics {
+                        message_format.print(diagnostic.report());
+                    }
+                    for (error, _) in &check.errors {
+                        message_format.print(error.report());
+                    }
+                    failed |= !check.errors.is_empty();
+                }
+                if failed {
+                    exit(1);
+                }
             }
             App::Watch {
                 no_format,
@@ -191,6 +423,8 @@ fn run() -> UiuaResult {
             }
             #[cfg(feature = "lsp")]
             App::Lsp => uiua::run_language_server(),
+            #[cfg(feature = "dap")]
+            App::Dap => uiua::run_debug_adapter(),
             App::Repl {
                 formatter_options,
                 #[cfg(feature = "audio")]
@@ -505,6 +739,28 @@ enum App {
         time_instrs: bool,
         #[clap(long, help = "Run the file in a specific mode")]
         mode: Option<RunMode>,
+        #[clap(flatten)]
+        policy_options: PolicyOptions,
+        #[clap(
+            long,
+            value_name = "PATH",
+            help = "Record the program's system calls to a transcript file"
+        )]
+        record: Option<PathBuf>,
+        #[clap(
+            long,
+            value_name = "PATH",
+            conflicts_with = "record",
+            help = "Replay the program's system calls from a transcript file"
+        )]
+        replay: Option<PathBuf>,
+        #[clap(
+            long,
+            value_enum,
+            default_value_t = MessageFormat::Human,
+            help = "The format of error and diagnostic messages"
+        )]
+        message_format: MessageFormat,
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
@@ -522,11 +778,46 @@ enum App {
         #[clap(trailing_var_arg = true)]
         args: Vec<String>,
     },
-    #[clap(about = "Format and test a file")]
+    #[clap(about = "Format and test a file or all files in a directory")]
     Test {
         path: Option<PathBuf>,
         #[clap(flatten)]
         formatter_options: FormatterOptions,
+        #[clap(
+            long,
+            help = "Only run tests whose name contains this string, or that contain this line"
+        )]
+        filter: Option<String>,
+        #[clap(long, help = "Run each top-level line of a test scope as its own test")]
+        per_line: bool,
+        #[clap(
+            long,
+            value_enum,
+            default_value_t = TestFormat::Human,
+            help = "The format of test results"
+        )]
+        format: TestFormat,
+        #[clap(
+            long,
+            value_enum,
+            default_value_t = MessageFormat::Human,
+            help = "The format of error and diagnostic messages"
+        )]
+        message_format: MessageFormat,
+    },
+    #[clap(about = "Check files for errors without running them")]
+    Check {
+        #[clap(help = "Files or directories to check")]
+        paths: Vec<PathBuf>,
+        #[clap(long, help = "Print the signature of each binding and top-level line")]
+        signatures: bool,
+        #[clap(
+            long,
+            value_enum,
+            default_value_t = MessageFormat::Human,
+            help = "The format of error and diagnostic messages"
+        )]
+        message_format: MessageFormat,
     },
     #[clap(about = "Run .ua files in the current directory when they change")]
     Watch {
@@ -548,10 +839,25 @@ enum App {
         path: Option<PathBuf>,
         #[clap(flatten)]
         formatter_options: FormatterOptions,
+        #[clap(
+            long,
+            help = "List files that are not formatted instead of formatting them, \
+                    including files in subdirectories"
+        )]
+        check: bool,
+        #[clap(
+            long,
+            help = "Print the changes formatting would make instead of formatting, \
+                    including files in subdirectories"
+        )]
+        diff: bool,
     },
     #[cfg(feature = "lsp")]
     #[clap(about = "Run the Language Server")]
     Lsp,
+    #[cfg(feature = "dap")]
+    #[clap(about = "Run the Debug Adapter Protocol server")]
+    Dap,
     #[clap(about = "Run very simple REPL")]
     Repl {
         #[clap(flatten)]
@@ -591,6 +897,192 @@ struct FormatterOptions {
     stdout: bool,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum MessageFormat {
+    /// Human-readable reports
+    Human,
+    /// One JSON object per message
+    Json,
+}
+
+impl MessageFormat {
+    fn print(self, report: Report) {
+        match self {
+            MessageFormat::Human => println!("{report}"),
+            MessageFormat::Json => println!("{}", report.to_json()),
+        }
+    }
+    /// Print the diagnostics collected by a runtime that does not print its own
+    fn print_diagnostics(self, rt: &mut Uiua) {
+        for diagnostic in rt.take_diagnostics() {
+            self.print(diagnostic.report());
+        }
+    }
+    fn unwrap_or_exit<T>(self, res: UiuaResult<T>) -> T {
+        res.unwrap_or_else(|e| {
+            self.print(e.report());
+            exit(1)
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum TestFormat {
+    /// Human-readable results
+    Human,
+    /// JUnit XML
+    Junit,
+    /// Test Anything Protocol
+    Tap,
+    /// One JSON object per test and one for the whole run
+    Json,
+}
+
+#[derive(clap::Args)]
+struct PolicyOptions {
+    #[clap(long, help = "Deny all system access that is not explicitly allowed")]
+    sandbox: bool,
+    #[clap(
+        long,
+        value_name = "PATHS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true,
+        help = "Allow reading files, optionally only in the given paths"
+    )]
+    allow_read: Option<Vec<PathBuf>>,
+    #[clap(
+        long,
+        value_name = "PATHS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true,
+        help = "Allow writing files, optionally only in the given paths"
+    )]
+    allow_write: Option<Vec<PathBuf>>,
+    #[clap(
+        long,
+        value_name = "HOSTS",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true,
+        help = "Allow TCP connections, optionally only to the given hosts or host:port pairs"
+    )]
+    allow_net: Option<Vec<String>>,
+    #[clap(long, help = "Allow running commands")]
+    allow_run: bool,
+    #[clap(long, help = "Allow reading environment variables")]
+    allow_env: bool,
+    #[clap(long, help = "Allow invoking paths with the default program")]
+    allow_invoke: bool,
+    #[clap(long, help = "Allow changing the current directory")]
+    allow_cd: bool,
+    #[clap(long, help = "Deny reading files")]
+    deny_read: bool,
+    #[clap(long, help = "Deny writing files")]
+    deny_write: bool,
+    #[clap(long, help = "Deny TCP connections")]
+    deny_net: bool,
+    #[clap(long, help = "Deny running commands")]
+    deny_run: bool,
+    #[clap(long, help = "Deny reading environment variables")]
+    deny_env: bool,
+    #[clap(long, help = "Deny invoking paths with the default program")]
+    deny_invoke: bool,
+    #[clap(long, help = "Deny changing the current directory")]
+    deny_cd: bool,
+}
+
+impl PolicyOptions {
+    /// Build a policy if any permission flags were passed
+    fn policy(self) -> Option<SysPolicy> {
+        let any_flags = self.sandbox
+            || self.allow_read.is_some()
+            || self.allow_write.is_some()
+            || self.allow_net.is_some()
+            || self.allow_run
+            || self.allow_env
+            || self.allow_invoke
+            || self.allow_cd
+            || self.deny_read
+            || self.deny_write
+            || self.deny_net
+            || self.deny_run
+            || self.deny_env
+            || self.deny_invoke
+            || self.deny_cd;
+        if !any_flags {
+            return None;
+        }
+        let mut policy = if self.sandbox {
+            SysPolicy::default()
+        } else {
+            SysPolicy::allow_all()
+        };
+        if let Some(paths) = self.allow_read {
+            policy = if paths.is_empty() {
+                policy.allow_read_all()
+            } else {
+                paths
+                    .into_iter()
+                    .fold(policy.deny_read(), SysPolicy::allow_read)
+            };
+        }
+        if let Some(paths) = self.allow_write {
+            policy = if paths.is_empty() {
+                policy.allow_write_all()
+            } else {
+                paths
+                    .into_iter()
+                    .fold(policy.deny_write(), SysPolicy::allow_write)
+            };
+        }
+        if let Some(hosts) = self.allow_net {
+            policy = if hosts.is_empty() {
+                policy.allow_net_all()
+            } else {
+                hosts
+                    .into_iter()
+                    .fold(policy.deny_net(), SysPolicy::allow_net)
+            };
+        }
+        if self.allow_run {
+            policy = policy.with_run(true);
+        }
+        if self.allow_env {
+            policy = policy.with_env(true);
+        }
+        if self.allow_invoke {
+            policy = policy.with_invoke(true);
+        }
+        if self.allow_cd {
+            policy = policy.with_change_directory(true);
+        }
+        if self.deny_read {
+            policy = policy.deny_read();
+        }
+        if self.deny_write {
+            policy = policy.deny_write();
+        }
+        if self.deny_net {
+            policy = policy.deny_net();
+        }
+        if self.deny_run {
+            policy = policy.with_run(false);
+        }
+        if self.deny_env {
+            policy = policy.with_env(false);
+        }
+        if self.deny_invoke {
+            policy = policy.with_invoke(false);
+        }
+        if self.deny_cd {
+            policy = policy.with_change_directory(false);
+        }
+        Some(policy)
+    }
+}
+
 #[cfg(feature = "audio")]
 #[derive(clap::Args)]
 struct AudioOptions {
@@ -622,6 +1114,27 @@ fn uiua_files() -> Vec<PathBuf> {
         .collect()
 }
 
+/// Get all .ua files in a directory and its subdirectories
+fn uiua_files_in(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if path.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                files.extend(uiua_files_in(&path));
+            }
+        } else if path.extension().is_some_and(|ext| ext == "ua") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
 const WATCHING: &str = "watching for changes...";
 fn print_watching() {
     #[cfg(feature = "raw_mode")]
@@ -710,6 +1223,504 @@ fn format_multi_files(config: &FormatConfig, stdout: bool) -> Result<(), UiuaErr
     Ok(())
 }
 
+/// Get a unified diff between two versions of a file
+fn unified_diff(path: &Path, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let mut ops = Vec::new();
+    diff_lines(&a, &b, &mut ops);
+    let changes: Vec<usize> = (ops.iter().enumerate())
+        .filter(|(_, (op, _))| *op != ' ')
+        .map(|(i, _)| i)
+        .collect();
+    let mut diff = format!("--- a/{0}\n+++ b/{0}\n", path.display());
+    let mut k = 0;
+    while k < changes.len() {
+        let mut last = k;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT + 1 {
+            last += 1;
+        }
+        let start = changes[k].saturating_sub(CONTEXT);
+        let end = (changes[last] + CONTEXT + 1).min(ops.len());
+        let a_before = ops[..start].iter().filter(|(op, _)| *op != '+').count();
+        let b_before = ops[..start].iter().filter(|(op, _)| *op != '-').count();
+        let a_len = ops[start..end].iter().filter(|(op, _)| *op != '+').count();
+        let b_len = ops[start..end].iter().filter(|(op, _)| *op != '-').count();
+        let a_start = if a_len == 0 { a_before } else { a_before + 1 };
+        let b_start = if b_len == 0 { b_before } else { b_before + 1 };
+        diff.push_str(&format!("@@ -{a_start},{a_len} +{b_start},{b_len} @@\n"));
+        for (op, line) in &ops[start..end] {
+            diff.push(*op);
+            diff.push_str(line);
+            if !line.ends_with('\n') {
+                diff.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        k = last + 1;
+    }
+    diff
+}
+
+/// Find the edits that turn `a` into `b` with Myers' linear-space diff algorithm
+fn diff_lines<'a>(a: &[&'a str], b: &[&'a str], ops: &mut Vec<(char, &'a str)>) {
+    let prefix = (a.iter().zip(b)).take_while(|(a, b)| a == b).count();
+    ops.extend(a[..prefix].iter().map(|&line| (' ', line)));
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = (a.iter().rev().zip(b.iter().rev()))
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (mid_a, mid_b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+    if mid_a.is_empty() {
+        ops.extend(mid_b.iter().map(|&line| ('+', line)));
+    } else if mid_b.is_empty() {
+        ops.extend(mid_a.iter().map(|&line| ('-', line)));
+    } else {
+        let (x, y, u, v) = middle_snake(mid_a, mid_b);
+        diff_lines(&mid_a[..x], &mid_b[..y], ops);
+        ops.extend(mid_a[x..u].iter().map(|&line| (' ', line)));
+        diff_lines(&mid_a[u..], &mid_b[v..], ops);
+    }
+    ops.extend(a[a.len() - suffix..].iter().map(|&line| (' ', line)));
+}
+
+/// Find the middle snake of the shortest edit script between `a` and `b`
+///
+/// Returns the start and end of the snake as `(x, y, u, v)`.
+/// `a` and `b` must not be empty or share a first or last line.
+fn middle_snake(a: &[&str], b: &[&str]) -> (usize, usize, usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2 + 1;
+    let index = |k: isize| (k + max) as usize;
+    let mut forward = vec![0; 2 * max as usize + 1];
+    let mut backward = vec![0; 2 * max as usize + 1];
+    for d in 0..max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || k != d && forward[index(k - 1)] < forward[index(k + 1)] {
+                forward[index(k + 1)]
+            } else {
+                forward[index(k - 1)] + 1
+            };
+            let (x0, y0) = (x, x - k);
+            let mut y = y0;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[index(k)] = x;
+            if odd && (k - delta).abs() < d && x + backward[index(delta - k)] >= n {
+                return (x0 as usize, y0 as usize, x as usize, y as usize);
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || k != d && backward[index(k - 1)] < backward[index(k + 1)] {
+                backward[index(k + 1)]
+            } else {
+                backward[index(k - 1)] + 1
+            };
+            let (x0, y0) = (x, x - k);
+            let mut y = y0;
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[index(k)] = x;
+            if !odd && (delta - k).abs() <= d && x + forward[index(delta - k)] >= n {
+                return (
+                    (n - x) as usize,
+                    (m - y) as usize,
+                    (n - x0) as usize,
+                    (m - y0) as usize,
+                );
+            }
+        }
+    }
+    unreachable!("the forward and backward paths always meet")
+}
+
+/// A test file's path, its results, and what it printed to stdout
+type TestFileResults = (PathBuf, UiuaResult<Vec<TestCase>>, String);
+
+/// A backend that captures standard output so that it does not end up in a
+/// machine-readable test report
+#[derive(Default)]
+struct CaptureSys {
+    stdout: Mutex<String>,
+}
+
+impl SysBackend for CaptureSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn save_error_color(&self, error: &UiuaError) {
+        NativeSys.save_error_color(error)
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.stdout.lock().push_str(s);
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        NativeSys.print_str_stderr(s)
+    }
+    fn print_str_trace(&self, s: &str) {
+        NativeSys.print_str_trace(s)
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        NativeSys.scan_line_stdin()
+    }
+    fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String> {
+        NativeSys.set_raw_mode(raw_mode)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        NativeSys.var(name)
+    }
+    fn term_size(&self) -> Result<(usize, usize), String> {
+        NativeSys.term_size()
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        NativeSys.file_exists(path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        NativeSys.list_dir(path)
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        NativeSys.is_file(path)
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        NativeSys.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        NativeSys.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        NativeSys.read(handle, count)
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        NativeSys.read_until(handle, delim)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        NativeSys.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        NativeSys.create_file(path)
+    }
+    fn open_file(&self, path: &Path) -> Result<Handle, String> {
+        NativeSys.open_file(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        NativeSys.file_read_all(path)
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        NativeSys.file_write_all(path, contents)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        NativeSys.sleep(seconds)
+    }
+    fn show_image(&self, image: DynamicImage) -> Result<(), String> {
+        NativeSys.show_image(image)
+    }
+    fn show_gif(&self, gif_bytes: Vec<u8>) -> Result<(), String> {
+        NativeSys.show_gif(gif_bytes)
+    }
+    fn play_audio(&self, wave_bytes: Vec<u8>) -> Result<(), String> {
+        NativeSys.play_audio(wave_bytes)
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        NativeSys.audio_sample_rate()
+    }
+    fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+        NativeSys.stream_audio(f)
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        NativeSys.tcp_listen(addr)
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        NativeSys.tcp_accept(handle)
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        NativeSys.tcp_connect(addr)
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<String, String> {
+        NativeSys.tcp_addr(handle)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        NativeSys.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        NativeSys.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        NativeSys.tcp_set_write_timeout(handle, timeout)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        NativeSys.close(handle)
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        NativeSys.invoke(path)
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        let (status, out, err) = NativeSys.run_command_capture(command, args)?;
+        self.print_str_stdout(&out)?;
+        self.print_str_stderr(&err)?;
+        Ok(status)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        NativeSys.run_command_capture(command, args)
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        NativeSys.change_directory(path)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        NativeSys.https_get(request, handle)
+    }
+    fn now(&self) -> f64 {
+        NativeSys.now()
+    }
+    fn rand(&self) -> f64 {
+        NativeSys.rand()
+    }
+}
+
+fn print_test_file(path: &Path, res: &UiuaResult<Vec<TestCase>>, message_format: MessageFormat) {
+    let cases = match res {
+        Ok(cases) => cases,
+        Err(e) => return message_format.print(e.report()),
+    };
+    if !cases.is_empty() {
+        println!("{}", path.display().to_string().bold());
+    }
+    for case in cases {
+        let mark = if case.passed() {
+            "✔".green()
+        } else {
+            "✘".red()
+        };
+        let duration = format!("({:.2}ms)", case.duration).bright_black();
+        println!("  {mark} {} {duration}", case.name);
+        if let Some(failure) = &case.failure {
+            message_format.print(failure.report());
+        }
+    }
+}
+
+/// Get the failure message of a test and its full uncolored report
+fn test_failure_message(report: Report) -> (String, String) {
+    let message = (report.messages.first())
+        .map(|(message, _)| message.clone())
+        .unwrap_or_default();
+    (message, report.color(false).to_string())
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Characters that are not allowed anywhere in an XML 1.0 document
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\0'..='\x1f' | '\u{fffe}' | '\u{ffff}' => escaped.extend(c.escape_unicode()),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn junit_test_report(files: &[TestFileResults], elapsed: f64) -> String {
+    let mut suites = String::new();
+    let (mut total, mut total_failures, mut total_errors) = (0, 0, 0);
+    for (path, res, output) in files {
+        let path = xml_escape(&path.display().to_string());
+        let mut cases = String::new();
+        let (tests, failures, errors, time) = match res {
+            Ok(cases_res) => {
+                let mut failures = 0;
+                for case in cases_res {
+                    let name = xml_escape(&case.name);
+                    let time = case.duration / 1000.0;
+                    cases.push_str(&format!(
+                        "    <testcase name=\"{name}\" classname=\"{path}\" \
+                        file=\"{path}\" line=\"{}\" time=\"{time:.6}\"",
+                        case.span.start.line
+                    ));
+                    if let Some(failure) = &case.failure {
+                        failures += 1;
+                        let (message, report) = test_failure_message(failure.report());
+                        cases.push_str(&format!(
+                            ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                            xml_escape(&message),
+                            xml_escape(&report)
+                        ));
+                    } else {
+                        cases.push_str("/>\n");
+                    }
+                }
+                let time = (cases_res.iter()).fold(0.0, |time, case| time + case.duration / 1000.0);
+                (cases_res.len(), failures, 0, time)
+            }
+            Err(e) => {
+                let (message, report) = test_failure_message(e.report());
+                cases.push_str(&format!(
+                    "    <testcase name=\"{path}\" classname=\"{path}\" file=\"{path}\" time=\"0\">\n      \
+                    <error message=\"{}\">{}</error>\n    </testcase>\n",
+                    xml_escape(&message),
+                    xml_escape(&report)
+                ));
+                (1, 0, 1, 0.0)
+            }
+        };
+        total += tests;
+        total_failures += failures;
+        total_errors += errors;
+        if !output.is_empty() {
+            cases.push_str(&format!(
+                "    <system-out>{}</system-out>\n",
+                xml_escape(output)
+            ));
+        }
+        suites.push_str(&format!(
+            "  <testsuite name=\"{path}\" tests=\"{tests}\" failures=\"{failures}\" \
+            errors=\"{errors}\" time=\"{time:.6}\">\n{cases}  </testsuite>\n"
+        ));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <testsuites name=\"uiua\" tests=\"{total}\" failures=\"{total_failures}\" \
+        errors=\"{total_errors}\" time=\"{elapsed:.6}\">\n{suites}</testsuites>\n"
+    )
+}
+
+/// Escape a TAP test description so that it stays on one line and
+/// `#` does not start a directive
+fn tap_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '#' => escaped.push_str("\\#"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn tap_test_report(files: &[TestFileResults]) -> String {
+    let mut count = 0;
+    let mut tests = String::new();
+    for (path, res, output) in files {
+        let path = path.display().to_string();
+        let mut test = |ok: bool, name: &str, duration: f64, failure: Option<Report>| {
+            count += 1;
+            let status = if ok { "ok" } else { "not ok" };
+            let description = tap_escape(&format!("{path}: {name}"));
+            tests.push_str(&format!("{status} {count} - {description}\n  ---\n"));
+            tests.push_str(&format!("  duration_ms: {duration:.3}\n"));
+            if let Some(report) = failure {
+                let (_, report) = test_failure_message(report);
+                tests.push_str("  message: |\n");
+                for line in report.lines() {
+                    tests.push_str(&format!("    {line}\n"));
+                }
+            }
+            tests.push_str("  ...\n");
+        };
+        match res {
+            Ok(cases) => {
+                for case in cases {
+                    let failure = case.failure.as_ref().map(TestFailure::report);
+                    test(case.passed(), &case.name, case.duration, failure);
+                }
+            }
+            Err(e) => test(false, "load", 0.0, Some(e.report())),
+        }
+        for line in output.lines() {
+            tests.push_str(&format!("# {line}\n"));
+        }
+    }
+    format!("TAP version 13\n1..{count}\n{tests}")
+}
+
+fn json_test_report(files: &[TestFileResults], elapsed: f64) -> String {
+    let mut lines = Vec::new();
+    let (mut passed, mut failed) = (0, 0);
+    for (path, res, output) in files {
+        let file = path.display().to_string();
+        if !output.is_empty() {
+            let json = serde_json::json!({
+                "type": "file",
+                "event": "output",
+                "file": file,
+                "output": output,
+            });
+            lines.push(json.to_string());
+        }
+        match res {
+            Ok(cases) => {
+                for case in cases {
+                    let mut json = serde_json::json!({
+                        "type": "test",
+                        "event": if case.passed() { "ok" } else { "failed" },
+                        "name": case.name,
+                        "file": file,
+                        "line": case.span.start.line,
+                        "exec_time": case.duration / 1000.0,
+                    });
+                    if let Some(failure) = &case.failure {
+                        let (_, report) = test_failure_message(failure.report());
+                        json["message"] = report.into();
+                        failed += 1;
+                    } else {
+                        passed += 1;
+                    }
+                    lines.push(json.to_string());
+                }
+            }
+            Err(e) => {
+                let (_, report) = test_failure_message(e.report());
+                failed += 1;
+                let json = serde_json::json!({
+                    "type": "file",
+                    "event": "failed",
+                    "file": file,
+                    "message": report,
+                });
+                lines.push(json.to_string());
+            }
+        }
+    }
+    let json = serde_json::json!({
+        "type": "suite",
+        "event": if failed == 0 { "ok" } else { "failed" },
+        "passed": passed,
+        "failed": failed,
+        "exec_time": elapsed,
+    });
+    lines.push(json.to_string());
+    lines.join("\n") + "\n"
+}
+
 fn print_stack(stack: &[Value], color: bool) {
     if stack.len() == 1 || !color {
         for value in stack {
@@ -734,72 +1745,59 @@ fn print_stack(stack: &[Value], color: bool) {
 }
 
 fn repl(mut rt: Uiua, color: bool, config: FormatConfig) {
-    let mut line_reader = DefaultEditor::new().expect("Failed to read from Stdin");
+    let mut line_reader: Editor<ReplHelper, FileHistory> =
+        Editor::new().expect("Failed to read from Stdin");
+    line_reader.set_helper(Some(ReplHelper::default()));
+    let history_path = repl_history_path();
+    if let Some(path) = &history_path {
+        _ = line_reader.load_history(path);
+    }
+    // The state from before the last line, for `:undo`
+    let mut undo: Option<Uiua> = None;
     let mut repl = |rt: &mut Uiua| -> Result<bool, UiuaError> {
         let mut code = match line_reader.readline("» ") {
             Ok(code) => code,
             Err(ReadlineError::Eof | ReadlineError::Interrupted) => return Ok(false),
             Err(_) => panic!("Failed to read from Stdin"),
         };
-        if code.is_empty() {
+        if code.trim().is_empty() {
             return Ok(true);
         }
 
-        match format_str(&code, &config) {
-            Ok(formatted) => {
-                code = formatted.output;
-                _ = line_reader.add_history_entry(&code);
+        let res = if let Some(command) = code.trim().strip_prefix(':') {
+            _ = line_reader.add_history_entry(code.trim());
+            if let Some(path) = &history_path {
+                _ = line_reader.append_history(path);
             }
-            Err(e) => {
-                _ = line_reader.add_history_entry(&code);
-                return Err(e);
-            }
-        }
-
-        print!("↪ ");
-        for span in spans(&code) {
-            let (r, g, b) = match span.value {
-                SpanKind::Primitive(prim) => match prim.class() {
-                    PrimClass::Stack => (209, 218, 236),
-                    PrimClass::Ocean => (3, 215, 217),
-                    PrimClass::Constant => (237, 94, 36),
-                    _ => {
-                        if let Some(margs) = prim.modifier_args() {
-                            if margs == 1 {
-                                (240, 195, 111)
-                            } else {
-                                (204, 107, 233)
-                            }
-                        } else {
-                            match prim.args() {
-                                Some(0) => (237, 94, 106),
-                                Some(1) => (149, 209, 106),
-                                Some(2) => (84, 176, 252),
-                                _ => (255, 255, 255),
-                            }
-                        }
-                    }
-                },
-                SpanKind::String => (32, 249, 252),
-                SpanKind::Number => (255, 136, 68),
-                SpanKind::Comment => (127, 127, 127),
-                SpanKind::Strand => (200, 200, 200),
-                SpanKind::Ident
-                | SpanKind::Signature
-                | SpanKind::Whitespace
-                | SpanKind::Placeholder
-                | SpanKind::Delimiter => (255, 255, 255),
-            };
-            print!("{}", span.span.as_str().truecolor(r, g, b));
+            repl_command(rt, command, &mut undo, color, &config)
+        } else {
+            let formatted = format_str(&code, &config);
+            if let Ok(formatted) = &formatted {
+                code = formatted.output.clone();
+            }
+            _ = line_reader.add_history_entry(&code);
+            if let Some(path) = &history_path {
+                _ = line_reader.append_history(path);
+            }
+            formatted?;
+            println!("↪ {}", highlight_code(&code).replace('\n', "\n  "));
+            run_repl_line(rt, &mut undo, |rt| rt.load_str(&code))
+                .map(|()| print_stack(rt.stack(), color))
+        };
+        if let Some(helper) = line_reader.helper_mut() {
+            helper.bindings = rt
+                .bound_names()
+                .iter()
+                .map(|name| name.to_string())
+                .collect();
         }
-        println!();
-
-        let res = rt.load_str(&code);
-        print_stack(&rt.take_stack(), color);
         res.map(|()| true)
     };
 
-    println!("Uiua {} (end with ctrl+C)\n", env!("CARGO_PKG_VERSION"));
+    println!(
+        "Uiua {} (end with ctrl+C, :help for commands)\n",
+        env!("CARGO_PKG_VERSION")
+    );
     loop {
         match repl(&mut rt) {
             Ok(true) => {}
@@ -810,3 +1808,530 @@ fn repl(mut rt: Uiua, color: bool, config: FormatConfig) {
         }
     }
 }
+
+/// Run some code in the REPL so that it can be undone
+///
+/// If the code fails, the stack and bindings are restored.
+fn run_repl_line(
+    rt: &mut Uiua,
+    undo: &mut Option<Uiua>,
+    f: impl FnOnce(&mut Uiua) -> UiuaResult,
+) -> UiuaResult {
+    let before = rt.clone();
+    let handle = rt.interrupt_handle();
+    handle.reset();
+    *REPL_INTERRUPT.lock() = Some(handle);
+    let res = f(rt);
+    *REPL_INTERRUPT.lock() = None;
+    if res.is_ok() {
+        *undo = Some(before);
+    } else {
+        *rt = before;
+    }
+    res
+}
+
+const REPL_COMMANDS: &[(&str, &str)] = &[
+    (":stack", "Show the stack"),
+    (":clear", "Clear the stack"),
+    (":bindings", "List the bindings and their signatures"),
+    (":doc <primitive>", "Show a primitive's documentation"),
+    (":load <path>", "Run a file"),
+    (":time <code>", "Run some code and show how long it took"),
+    (
+        ":undo",
+        "Restore the stack and bindings from before the last line",
+    ),
+    (":help", "Show this list of commands"),
+];
+
+/// Run a REPL command, not including the leading `:`
+fn repl_command(
+    rt: &mut Uiua,
+    command: &str,
+    undo: &mut Option<Uiua>,
+    color: bool,
+    config: &FormatConfig,
+) -> UiuaResult {
+    let (name, arg) = (command.split_once(char::is_whitespace))
+        .map_or((command, ""), |(name, arg)| (name, arg.trim()));
+    match name {
+        "stack" => print_stack(rt.stack(), color),
+        "clear" => run_repl_line(rt, undo, |rt| {
+            rt.take_stack();
+            Ok(())
+        })?,
+        "bindings" => print_bindings(rt),
+        "doc" => {
+            let prim = Primitive::from_format_name(arg).or_else(|| {
+                Primitive::all().find(|prim| {
+                    prim.name() == arg
+                        || prim.glyph().is_some_and(|glyph| arg == glyph.to_string())
+                        || prim.ascii().is_some_and(|ascii| arg == ascii.to_string())
+                })
+            });
+            match prim {
+                Some(prim) => print_prim_doc(prim),
+                None => eprintln!("Unknown primitive `{arg}`"),
+            }
+        }
+        "load" => {
+            run_repl_line(rt, undo, |rt| rt.load_file(arg))?;
+            print_stack(rt.stack(), color);
+        }
+        "time" => {
+            let code = format_str(arg, config)?.output;
+            println!("↪ {}", highlight_code(&code).replace('\n', "\n  "));
+            let start = Instant::now();
+            run_repl_line(rt, undo, |rt| rt.load_str(&code))?;
+            let elapsed = start.elapsed();
+            print_stack(rt.stack(), color);
+            println!(
+                "{}",
+                format!("⏲ {:.3}ms", elapsed.as_secs_f64() * 1000.0).bright_black()
+            );
+        }
+        "undo" => match undo.take() {
+            Some(before) => {
+                *rt = before;
+                print_stack(rt.stack(), color);
+            }
+            None => eprintln!("Nothing to undo"),
+        },
+        "help" => {
+            for (command, description) in REPL_COMMANDS {
+                println!("{command:<18} {description}");
+            }
+        }
+        _ => eprintln!("Unknown command `:{name}`, use :help to list commands"),
+    }
+    Ok(())
+}
+
+fn print_bindings(rt: &Uiua) {
+    let values = rt.all_values_is_scope();
+    let mut names = rt.bound_names();
+    names.sort();
+    for name in names {
+        let signature = rt
+            .binding_signature(&name)
+            .map(|sig| sig.to_string())
+            .unwrap_or_default();
+        let value = values.get(&name).map(|value| {
+            let shown = value.show();
+            let mut lines = shown.lines();
+            let first = lines.next().unwrap_or_default().to_string();
+            if lines.next().is_some() {
+                format!(" = {first} …")
+            } else {
+                format!(" = {first}")
+            }
+        });
+        println!(
+            "{name} {}{}",
+            signature.bright_black(),
+            value.unwrap_or_default()
+        );
+    }
+}
+
+fn print_prim_doc(prim: Primitive) {
+    let mut header = match prim.glyph() {
+        Some(glyph) => format!("{glyph} {}", prim.name()),
+        None => prim.name().to_string(),
+    };
+    if let Some(margs) = prim.modifier_args() {
+        header.push_str(&format!(" ({margs}-function modifier)"));
+    } else if let (Some(args), Some(outputs)) = (prim.args(), prim.outputs()) {
+        header.push_str(&format!(
+            " {}",
+            Signature::new(args as usize, outputs as usize)
+        ));
+    }
+    println!("{}", highlight_code(&header));
+    let Some(doc) = prim.doc() else {
+        return;
+    };
+    let text = |fragments: &[PrimDocFragment]| -> String {
+        (fragments.iter())
+            .map(|frag| match frag {
+                PrimDocFragment::Text(text) => text.clone(),
+                PrimDocFragment::Code(code) => highlight_code(code),
+                PrimDocFragment::Emphasis(text) => text.italic().to_string(),
+                PrimDocFragment::Strong(text) => text.bold().to_string(),
+                PrimDocFragment::Link { text, .. } => text.underline().to_string(),
+                PrimDocFragment::Primitive { prim, named } => {
+                    let formatted = match prim.glyph() {
+                        Some(glyph) if *named => format!("{glyph} {}", prim.name()),
+                        Some(glyph) => glyph.to_string(),
+                        None => prim.name().to_string(),
+                    };
+                    highlight_code(&formatted)
+                }
+            })
+            .collect()
+    };
+    println!("{}", text(&doc.short));
+    for line in &doc.lines {
+        match line {
+            PrimDocLine::Text(fragments) => println!("{}", text(fragments)),
+            PrimDocLine::Example(example) => {
+                println!(
+                    "  {}",
+                    highlight_code(example.input()).replace('\n', "\n  ")
+                )
+            }
+        }
+    }
+}
+
+/// Where the REPL history is saved, in the home directory
+fn repl_history_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".uiua_history"))
+}
+
+/// Color code the same way the website does
+fn highlight_code(code: &str) -> String {
+    let mut colored = String::new();
+    let mut end = 0;
+    for span in spans(code) {
+        let (start, span_end) = (span.span.start.byte_pos, span.span.end.byte_pos);
+        if start < end {
+            continue;
+        }
+        colored.push_str(&code[end..start]);
+        let (r, g, b) = match span.value {
+            SpanKind::Primitive(prim) => match prim.class() {
+                PrimClass::Stack => (209, 218, 236),
+                PrimClass::Ocean => (3, 215, 217),
+                PrimClass::Constant => (237, 94, 36),
+                _ => {
+                    if let Some(margs) = prim.modifier_args() {
+                        if margs == 1 {
+                            (240, 195, 111)
+                        } else {
+                            (204, 107, 233)
+                        }
+                    } else {
+                        match prim.args() {
+                            Some(0) => (237, 94, 106),
+                            Some(1) => (149, 209, 106),
+                            Some(2) => (84, 176, 252),
+                            _ => (255, 255, 255),
+                        }
+                    }
+                }
+            },
+            SpanKind::String => (32, 249, 252),
+            SpanKind::Number => (255, 136, 68),
+            SpanKind::Comment => (127, 127, 127),
+            SpanKind::Strand => (200, 200, 200),
+            SpanKind::Ident
+            | SpanKind::Signature
+            | SpanKind::Whitespace
+            | SpanKind::Placeholder
+            | SpanKind::Delimiter => (255, 255, 255),
+        };
+        colored.push_str(&span.span.as_str().truecolor(r, g, b).to_string());
+        end = span_end;
+    }
+    colored.push_str(&code[end.min(code.len())..]);
+    colored
+}
+
+/// Whether some code has unclosed delimiters or test scopes, so more lines are expected
+fn is_incomplete(code: &str) -> bool {
+    let mut depth = 0;
+    for span in spans(code) {
+        if span.value == SpanKind::Delimiter {
+            match span.span.as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    let scope_markers = code.lines().filter(|line| line.trim() == "---").count();
+    depth > 0 || scope_markers % 2 == 1
+}
+
+/// Multiline input, completion, and highlighting for the REPL
+#[derive(Default)]
+struct ReplHelper {
+    /// The names bound so far
+    bindings: Vec<String>,
+}
+
+impl Helper for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(if is_incomplete(ctx.input()) {
+            ValidationResult::Incomplete
+        } else {
+            ValidationResult::Valid(None)
+        })
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned(highlight_code(line))
+    }
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_ident_char(*c) || *c == '&')
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let word = &line[start..pos];
+        if word.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+        let mut candidates = Vec::new();
+        // A prefix of one or more primitive names
+        if let Some(prims) = Primitive::from_format_name_multi(word) {
+            let replacement: String = prims.iter().map(|(prim, _)| prim.to_string()).collect();
+            candidates.push(Pair {
+                display: replacement.clone(),
+                replacement,
+            });
+        }
+        let lower = word.to_lowercase();
+        for prim in Primitive::non_deprecated() {
+            if !prim.name().starts_with(&lower) {
+                continue;
+            }
+            let (display, replacement) = match prim.glyph() {
+                Some(glyph) => (format!("{glyph} {}", prim.name()), glyph.to_string()),
+                None => (prim.name().to_string(), prim.name().to_string()),
+            };
+            if !candidates.iter().any(|c| c.replacement == replacement) {
+                candidates.push(Pair {
+                    display,
+                    replacement,
+                });
+            }
+        }
+        for name in &self.bindings {
+            if name.starts_with(word) && !candidates.iter().any(|c| &c.replacement == name) {
+                candidates.push(Pair {
+                    display: name.clone(),
+                    replacement: name.clone(),
+                });
+            }
+        }
+        Ok((start, candidates))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Get the results of a file with a passing and a failing test and a file that fails to load
+    fn test_files() -> Vec<TestFileResults> {
+        let code = "---\n# Adds\n⍤⊃⋅∘≍ 3 +1 2\n---\n---\n# Fails <#>\n⍤⊃⋅∘≍ 3 +1 1\n---\n";
+        let path = PathBuf::from("a.ua");
+        let mut cases = (Uiua::with_native_sys())
+            .run_tests(code, Some(&path), &TestOptions::default())
+            .unwrap();
+        cases[0].name = "back\\slash\nnewline".into();
+        let missing = Path::new("missing/b.ua");
+        let error = Uiua::with_native_sys().run_test_file(missing, &TestOptions::default());
+        vec![
+            (path, Ok(cases), "out\n".into()),
+            (missing.into(), error, String::new()),
+        ]
+    }
+
+    #[test]
+    fn xml_escapes() {
+        assert_eq!(
+            xml_escape("<a b=\"c\">&'d'</a>"),
+            "&lt;a b=&quot;c&quot;&gt;&amp;&apos;d&apos;&lt;/a&gt;"
+        );
+        assert_eq!(
+            xml_escape("a\tb\nc\u{1}\u{ffff}é"),
+            "a\tb\nc\\u{1}\\u{ffff}é"
+        );
+    }
+
+    #[test]
+    fn junit_report() {
+        let report = junit_test_report(&test_files(), 1.5);
+        assert!(report.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(report.contains(
+            "<testsuites name=\"uiua\" tests=\"3\" failures=\"1\" errors=\"1\" time=\"1.500000\">"
+        ));
+        assert!(report.contains("<testsuite name=\"a.ua\" tests=\"2\" failures=\"1\" errors=\"0\""));
+        assert!(report.contains(
+            "<testcase name=\"Fails &lt;#&gt;\" classname=\"a.ua\" file=\"a.ua\" line=\"5\""
+        ));
+        assert!(report.contains(
+            "<failure message=\"Assertion failed: expected 3, but got 2\">Error: Assertion failed"
+        ));
+        assert!(report.contains("<system-out>out\n</system-out>"));
+        assert!(report
+            .contains("<testsuite name=\"missing/b.ua\" tests=\"1\" failures=\"0\" errors=\"1\""));
+        assert!(report.contains("<error message=\"failed to load missing/b.ua: "));
+        assert!(report.ends_with("</testsuites>\n"));
+    }
+
+    #[test]
+    fn tap_report() {
+        let report = tap_test_report(&test_files());
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(
+            lines[..3],
+            [
+                "TAP version 13",
+                "1..3",
+                "ok 1 - a.ua: back\\\\slash\\nnewline"
+            ]
+        );
+        assert!(lines.contains(&"not ok 2 - a.ua: Fails <\\#>"));
+        assert!(lines.contains(&"    Error: Assertion failed: expected 3, but got 2"));
+        assert!(lines.contains(&"# out"));
+        assert!(lines.contains(&"not ok 3 - missing/b.ua: load"));
+        assert!(
+            (lines.iter()).any(|line| line.starts_with("    Error: failed to load missing/b.ua: "))
+        );
+    }
+
+    #[test]
+    fn json_report() {
+        let report = json_test_report(&test_files(), 1.5);
+        let events: Vec<serde_json::Value> = (report.lines())
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let fields = |event: &serde_json::Value| {
+            (
+                event["type"].as_str().unwrap().to_string(),
+                event["event"].as_str().unwrap().to_string(),
+            )
+        };
+        let kinds: Vec<(String, String)> = events.iter().map(fields).collect();
+        let expected = [
+            ("file", "output"),
+            ("test", "ok"),
+            ("test", "failed"),
+            ("file", "failed"),
+            ("suite", "failed"),
+        ];
+        assert_eq!(kinds, expected.map(|(ty, event)| (ty.into(), event.into())));
+        assert_eq!(events[0]["output"], "out\n");
+        assert_eq!(events[1]["name"], "back\\slash\nnewline");
+        assert_eq!(events[2]["name"], "Fails <#>");
+        assert_eq!(events[2]["line"], 5);
+        assert!(events[2]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Error: Assertion failed"));
+        assert!(events[3]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Error: failed to load missing/b.ua: "));
+        assert_eq!(events[4]["passed"], 1);
+        assert_eq!(events[4]["failed"], 2);
+        assert_eq!(events[4]["exec_time"], 1.5);
+    }
+
+    fn hunk_headers(old: &str, new: &str) -> Vec<String> {
+        (unified_diff(Path::new("a.ua"), old, new).lines())
+            .filter(|line| line.starts_with("@@"))
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn unified_diff_hunks() {
+        assert_eq!(
+            unified_diff(Path::new("a.ua"), "a\nb\nc\n", "a\nB\nc\n"),
+            "--- a/a.ua\n+++ b/a.ua\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+        assert_eq!(hunk_headers("", "a\n"), ["@@ -0,0 +1,1 @@"]);
+        assert_eq!(hunk_headers("a\n", ""), ["@@ -1,1 +0,0 @@"]);
+        assert_eq!(hunk_headers("a\n", "a\n"), Vec::<String>::new());
+
+        let old: String = (1..=20).map(|i| format!("{i}\n")).collect();
+        let new = old
+            .replacen("2\n", "two\n", 1)
+            .replace("\n19\n", "\n19\n19.5\n");
+        assert_eq!(
+            hunk_headers(&old, &new),
+            ["@@ -1,5 +1,5 @@", "@@ -17,4 +17,5 @@"]
+        );
+        let new = old.replace("\n8\n", "\n").replace("\n13\n", "\n");
+        assert_eq!(hunk_headers(&old, &new), ["@@ -5,12 +5,10 @@"]);
+    }
+
+    #[test]
+    fn diff_lines_minimal() {
+        let words = ["a\n", "b\n", "c\n"];
+        let mut seed = 1u64;
+        let mut random_lines = |len: u64| -> Vec<&str> {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            let len = (seed >> 33) % len;
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    words[(seed >> 33) as usize % words.len()]
+                })
+                .collect()
+        };
+        for _ in 0..1000 {
+            let (a, b) = (random_lines(12), random_lines(12));
+            let mut ops = Vec::new();
+            diff_lines(&a, &b, &mut ops);
+            let kept = |skip: char| -> Vec<&str> {
+                (ops.iter())
+                    .filter(|(op, _)| *op != skip)
+                    .map(|(_, line)| *line)
+                    .collect()
+            };
+            assert_eq!(kept('+'), a);
+            assert_eq!(kept('-'), b);
+            let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+            for i in (0..a.len()).rev() {
+                for j in (0..b.len()).rev() {
+                    lcs[i][j] = if a[i] == b[j] {
+                        lcs[i + 1][j + 1] + 1
+                    } else {
+                        lcs[i + 1][j].max(lcs[i][j + 1])
+                    };
+                }
+            }
+            let edits = ops.iter().filter(|(op, _)| *op != ' ').count();
+            assert_eq!(edits, a.len() + b.len() - 2 * lcs[0][0], "{a:?} {b:?}");
+        }
+    }
+
+    #[test]
+    fn unified_diff_large() {
+        let old: String = (0..200_000).map(|i| format!("{}\n", i % 1000)).collect();
+        let new = old.replacen("500\n", "five hundred\n", 1) + "end\n";
+        assert_eq!(
+            hunk_headers(&old, &new),
+            ["@@ -498,7 +498,7 @@", "@@ -199998,3 +199998,4 @@"]
+        );
+    }
+}
@@ -0,0 +1,566 @@
+//! Reading and writing NumPy `.npy` and `.npz` files
+
+use ecow::EcoVec;
+
+use crate::{Array, Boxed, Complex, Shape, Value};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+impl Value {
+    /// Encode the value in the NumPy `.npy` format
+    ///
+    /// Numeric arrays are written as `float64`, complex arrays as `complex128`,
+    /// and byte arrays as `uint8`.
+    pub fn to_npy_bytes(&self) -> Result<Vec<u8>, String> {
+        let (descr, data): (_, Vec<u8>) = match self {
+            Value::Num(arr) => (
+                "<f8",
+                arr.data.iter().flat_map(|n| n.to_le_bytes()).collect(),
+            ),
+            #[cfg(feature = "bytes")]
+            Value::Byte(arr) => ("|u1", arr.data.to_vec()),
+            Value::Complex(arr) => (
+                "<c16",
+                (arr.data.iter())
+                    .flat_map(|c| c.re.to_le_bytes().into_iter().chain(c.im.to_le_bytes()))
+                    .collect(),
+            ),
+            value => {
+                return Err(format!(
+                    "Cannot encode a {} array as npy",
+                    value.type_name()
+                ))
+            }
+        };
+        let shape = match self.shape() {
+            [n] => format!("({n},)"),
+            shape => format!(
+                "({})",
+                (shape.iter().map(|n| n.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut header =
+            format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+        // The header is padded so that the data is 64-byte aligned
+        let (version, prefix_len) = if header.len() + 11 <= u16::MAX as usize {
+            (1, 10)
+        } else {
+            (2, 12)
+        };
+        let unpadded = prefix_len + header.len() + 1;
+        header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+        header.push('\n');
+        let mut bytes = Vec::with_capacity(prefix_len + header.len() + data.len());
+        bytes.extend_from_slice(NPY_MAGIC);
+        bytes.extend([version, 0]);
+        if version == 1 {
+            bytes.extend((header.len() as u16).to_le_bytes());
+        } else {
+            bytes.extend((header.len() as u32).to_le_bytes());
+        }
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend(data);
+        Ok(bytes)
+    }
+    /// Decode a value from the NumPy `.npy` format
+    ///
+    /// Supported dtypes are floats, signed and unsigned integers, bools, and complex numbers.
+    /// Integers and bools become numeric arrays, or byte arrays if they fit and the `bytes` feature is enabled.
+    pub fn from_npy_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let rest = (bytes.strip_prefix(NPY_MAGIC)).ok_or("Data is not in the npy format")?;
+        let (header_len, rest) = match rest {
+            [1, _, a, b, rest @ ..] => (u16::from_le_bytes([*a, *b]) as usize, rest),
+            [2 | 3, _, a, b, c, d, rest @ ..] => {
+                (u32::from_le_bytes([*a, *b, *c, *d]) as usize, rest)
+            }
+            [major, ..] => return Err(format!("Unsupported npy version {major}")),
+            _ => return Err("npy data is truncated".into()),
+        };
+        if rest.len() < header_len {
+            return Err("npy data is truncated".into());
+        }
+        let (header, data) = rest.split_at(header_len);
+        let header = std::str::from_utf8(header).map_err(|_| "npy header is not valid text")?;
+        let descr = header_field(header, "descr")?;
+        let descr = match descr.chars().next() {
+            Some(q @ ('\'' | '"')) => descr[1..].split(q).next().unwrap_or_default(),
+            _ => return Err(format!("Unsupported npy dtype {descr}")),
+        };
+        let fortran_order = header_field(header, "fortran_order")?.starts_with("True");
+        let shape_str = header_field(header, "shape")?;
+        let shape_str = (shape_str.strip_prefix('('))
+            .and_then(|s| s.split(')').next())
+            .ok_or("Invalid npy shape")?;
+        let mut shape: Shape = (shape_str.split(','))
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<usize>().map_err(|_| "Invalid npy shape"))
+            .collect::<Result<_, _>>()?;
+        let count = (shape.iter())
+            .try_fold(1usize, |acc, &dim| acc.checked_mul(dim))
+            .ok_or("npy shape is too large")?;
+
+        let mut chars = descr.chars();
+        let big_endian = match chars.next() {
+            Some('<' | '|' | '=') => false,
+            Some('>') => true,
+            _ => return Err(format!("Unsupported npy dtype {descr:?}")),
+        };
+        let kind = chars.next().unwrap_or_default();
+        let size: usize = chars.as_str().parse().unwrap_or(0);
+        if size == 0 {
+            return Err(format!("Unsupported npy dtype {descr:?}"));
+        }
+        let byte_count = count.checked_mul(size).ok_or("npy shape is too large")?;
+        if data.len() < byte_count {
+            return Err("npy data is truncated".into());
+        }
+        let data = &data[..byte_count];
+        macro_rules! read {
+            ($ty:ty) => {
+                data.chunks_exact(size).map(|chunk| {
+                    let bytes = chunk.try_into().unwrap();
+                    if big_endian {
+                        <$ty>::from_be_bytes(bytes)
+                    } else {
+                        <$ty>::from_le_bytes(bytes)
+                    }
+                })
+            };
+        }
+        // Fortran-order data has its axes reversed
+        if fortran_order {
+            shape.reverse();
+        }
+        let mut value: Value = match (kind, size) {
+            ('f', 8) => read!(f64).collect::<EcoVec<_>>().into_array(shape),
+            ('f', 4) => read!(f32)
+                .map(f64::from)
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('i', 1) => read!(i8)
+                .map(f64::from)
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('i', 2) => read!(i16)
+                .map(f64::from)
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('i', 4) => read!(i32)
+                .map(f64::from)
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('i', 8) => read!(i64)
+                .map(|i| i as f64)
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('u' | 'b', 1) => Array::<u8>::new(shape, data).into(),
+            ('u', 2) => read!(u16)
+                .map(f64::from)
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('u', 4) => read!(u32)
+                .map(f64::from)
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('u', 8) => read!(u64)
+                .map(|u| u as f64)
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('c', 16) => (data.chunks_exact(16))
+                .map(|chunk| {
+                    let (re, im) = chunk.split_at(8);
+                    let (re, im) = (re.try_into().unwrap(), im.try_into().unwrap());
+                    if big_endian {
+                        Complex::new(f64::from_be_bytes(re), f64::from_be_bytes(im))
+                    } else {
+                        Complex::new(f64::from_le_bytes(re), f64::from_le_bytes(im))
+                    }
+                })
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            ('c', 8) => (data.chunks_exact(8))
+                .map(|chunk| {
+                    let (re, im) = chunk.split_at(4);
+                    let (re, im) = (re.try_into().unwrap(), im.try_into().unwrap());
+                    if big_endian {
+                        Complex::new(f32::from_be_bytes(re).into(), f32::from_be_bytes(im).into())
+                    } else {
+                        Complex::new(f32::from_le_bytes(re).into(), f32::from_le_bytes(im).into())
+                    }
+                })
+                .collect::<EcoVec<_>>()
+                .into_array(shape),
+            _ => return Err(format!("Unsupported npy dtype {descr:?}")),
+        };
+        if fortran_order {
+            value.transpose_all();
+        }
+        Ok(value)
+    }
+}
+
+trait IntoArray {
+    fn into_array(self, shape: Shape) -> Value;
+}
+
+impl<T: crate::ArrayValue> IntoArray for EcoVec<T>
+where
+    Value: From<Array<T>>,
+{
+    fn into_array(self, shape: Shape) -> Value {
+        Array::new(shape, self).into()
+    }
+}
+
+impl Value {
+    /// Reverse the order of all axes
+    fn transpose_all(&mut self) {
+        let mut shape = self.shape().to_vec();
+        shape.reverse();
+        let count: usize = shape.iter().product();
+        // For each index in the result, find the index in the original
+        let mut multi_index = vec![0; shape.len()];
+        let indices: Vec<usize> = (0..count)
+            .map(|i| {
+                let mut rem = i;
+                for (index, &dim) in multi_index.iter_mut().zip(&shape).rev() {
+                    *index = rem % dim;
+                    rem /= dim;
+                }
+                let mut orig = 0;
+                let mut stride = 1;
+                for (&index, &dim) in multi_index.iter().zip(&shape) {
+                    orig += index * stride;
+                    stride *= dim;
+                }
+                orig
+            })
+            .collect();
+        fn permute<T: Clone>(data: &[T], indices: &[usize]) -> EcoVec<T> {
+            indices.iter().map(|&i| data[i].clone()).collect()
+        }
+        let result_shape: Shape = shape.into_iter().collect();
+        *self = match &*self {
+            Value::Num(arr) => permute(&arr.data, &indices).into_array(result_shape),
+            #[cfg(feature = "bytes")]
+            Value::Byte(arr) => permute(&arr.data, &indices).into_array(result_shape),
+            Value::Complex(arr) => permute(&arr.data, &indices).into_array(result_shape),
+            Value::Char(arr) => permute(&arr.data, &indices).into_array(result_shape),
+            Value::Box(arr) => permute(&arr.data, &indices).into_array(result_shape),
+        };
+    }
+}
+
+/// Find the value of a field in an npy header
+fn header_field<'a>(header: &'a str, name: &str) -> Result<&'a str, String> {
+    let start = ["'", "\""]
+        .into_iter()
+        .find_map(|q| {
+            let key = format!("{q}{name}{q}");
+            header.find(&key).map(|i| i + key.len())
+        })
+        .ok_or_else(|| format!("npy header is missing {name:?}"))?;
+    (header[start..].trim_start().strip_prefix(':'))
+        .map(str::trim_start)
+        .ok_or_else(|| format!("Invalid npy header field {name:?}"))
+}
+
+/// Decode the arrays in a NumPy `.npz` archive
+///
+/// Array names do not include the `.npy` extension.
+pub fn npz_bytes_to_values(bytes: &[u8]) -> Result<Vec<(String, Value)>, String> {
+    npz_bytes_to_values_limited(bytes, usize::MAX)
+}
+
+/// Decode the arrays in a `.npz` archive whose arrays decompress to at most `limit` bytes
+fn npz_bytes_to_values_limited(bytes: &[u8], limit: usize) -> Result<Vec<(String, Value)>, String> {
+    (zip::read_entries(bytes, limit)?.into_iter())
+        .map(|(name, data)| {
+            let name = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+            let value = Value::from_npy_bytes(&data).map_err(|e| format!("In {name}: {e}"))?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Encode arrays into a NumPy `.npz` archive
+///
+/// The `.npy` extension is added to each array name.
+pub fn values_to_npz_bytes<'a>(
+    values: impl IntoIterator<Item = (&'a str, &'a Value)>,
+) -> Result<Vec<u8>, String> {
+    let entries = (values.into_iter())
+        .map(|(name, value)| Ok((format!("{name}.npy"), value.to_npy_bytes()?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    zip::write_entries(&entries)
+}
+
+/// Decode a `.npz` archive into a table of names and arrays
+///
+/// The arrays must decompress to at most `limit` bytes.
+pub(crate) fn npz_bytes_to_table(bytes: &[u8], limit: usize) -> Result<Value, String> {
+    let values = npz_bytes_to_values_limited(bytes, limit)?;
+    let shape: Shape = [values.len(), 2].into_iter().collect();
+    let data: EcoVec<Boxed> = (values.into_iter())
+        .flat_map(|(name, value)| [Boxed(name.into()), Boxed(value)])
+        .collect();
+    Ok(Array::new(shape, data).into())
+}
+
+/// Encode a table of names and arrays as a `.npz` archive
+pub(crate) fn table_to_npz_bytes(table: &Value) -> Result<Vec<u8>, String> {
+    let requirement = "npz data must be a rank 2 box array with a row for each name and array";
+    let Value::Box(arr) = table else {
+        return Err(requirement.into());
+    };
+    let is_table = match arr.shape() {
+        [_, 2] => true,
+        [n] => n % 2 == 0,
+        _ => false,
+    };
+    if !is_table {
+        return Err(requirement.into());
+    }
+    let names = (arr.data.chunks_exact(2))
+        .map(|pair| match &pair[0].0 {
+            Value::Char(name) if name.rank() == 1 => Ok(name.data.iter().collect::<String>()),
+            _ => Err("npz array names must be strings".to_string()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let values = (arr.data.chunks_exact(2)).map(|pair| &pair[1].0);
+    values_to_npz_bytes(names.iter().map(String::as_str).zip(values))
+}
+
+/// A minimal zip implementation for `.npz` archives
+mod zip {
+    const LOCAL_HEADER: u32 = 0x04034b50;
+    const CENTRAL_HEADER: u32 = 0x02014b50;
+    const END_OF_CENTRAL_DIR: u32 = 0x06054b50;
+    const ZIP64_END_OF_CENTRAL_DIR: u32 = 0x06064b50;
+    const ZIP64_LOCATOR: u32 = 0x07064b50;
+    const TRUNCATED: &str = "npz archive is truncated";
+
+    /// Add offsets from the archive, which may be too large to be valid
+    fn add(a: usize, b: usize) -> Result<usize, String> {
+        a.checked_add(b).ok_or_else(|| TRUNCATED.into())
+    }
+    fn to_usize(n: u64) -> Result<usize, String> {
+        usize::try_from(n).map_err(|_| TRUNCATED.into())
+    }
+    fn slice_at(bytes: &[u8], i: usize, len: usize) -> Result<&[u8], String> {
+        bytes.get(i..add(i, len)?).ok_or_else(|| TRUNCATED.into())
+    }
+    fn u16_at(bytes: &[u8], i: usize) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(
+            slice_at(bytes, i, 2)?.try_into().unwrap(),
+        ))
+    }
+    fn u32_at(bytes: &[u8], i: usize) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(
+            slice_at(bytes, i, 4)?.try_into().unwrap(),
+        ))
+    }
+    fn u64_at(bytes: &[u8], i: usize) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(
+            slice_at(bytes, i, 8)?.try_into().unwrap(),
+        ))
+    }
+
+    /// Read the entries of an archive whose entries decompress to at most `limit` bytes
+    pub fn read_entries(bytes: &[u8], limit: usize) -> Result<Vec<(String, Vec<u8>)>, String> {
+        // Find the end of central directory record
+        let search_start = bytes.len().saturating_sub(22 + u16::MAX as usize);
+        let eocd = (search_start..bytes.len().saturating_sub(21))
+            .rev()
+            .find(|&i| u32_at(bytes, i) == Ok(END_OF_CENTRAL_DIR))
+            .ok_or("Data is not an npz archive")?;
+        let mut count = u16_at(bytes, eocd + 10)? as u64;
+        let mut cd_offset = u32_at(bytes, eocd + 16)? as u64;
+        if (count == 0xFFFF || cd_offset == 0xFFFF_FFFF)
+            && eocd >= 20
+            && u32_at(bytes, eocd - 20)? == ZIP64_LOCATOR
+        {
+            let zip64_eocd = to_usize(u64_at(bytes, eocd - 12)?)?;
+            if u32_at(bytes, zip64_eocd)? != ZIP64_END_OF_CENTRAL_DIR {
+                return Err("Invalid zip64 npz archive".into());
+            }
+            count = u64_at(bytes, add(zip64_eocd, 32)?)?;
+            cd_offset = u64_at(bytes, add(zip64_eocd, 48)?)?;
+        }
+        let mut entries = Vec::new();
+        let mut remaining = limit;
+        let mut i = to_usize(cd_offset)?;
+        for _ in 0..count {
+            if u32_at(bytes, i)? != CENTRAL_HEADER {
+                return Err("Invalid npz archive".into());
+            }
+            let method = u16_at(bytes, add(i, 10)?)?;
+            let crc = u32_at(bytes, add(i, 16)?)?;
+            let mut compressed_size = u32_at(bytes, add(i, 20)?)? as u64;
+            let mut size = u32_at(bytes, add(i, 24)?)? as u64;
+            let name_len = u16_at(bytes, add(i, 28)?)? as usize;
+            let extra_len = u16_at(bytes, add(i, 30)?)? as usize;
+            let comment_len = u16_at(bytes, add(i, 32)?)? as usize;
+            let mut local_offset = u32_at(bytes, add(i, 42)?)? as u64;
+            let name = slice_at(bytes, add(i, 46)?, name_len)?;
+            let name = String::from_utf8_lossy(name).into_owned();
+            // Zip64 sizes and offsets are stored in an extra field
+            let mut j = add(i, 46 + name_len)?;
+            let extra_end = add(j, extra_len)?;
+            while j + 4 <= extra_end {
+                let id = u16_at(bytes, j)?;
+                let len = u16_at(bytes, j + 2)? as usize;
+                if id == 1 {
+                    let mut k = j + 4;
+                    for field in [&mut size, &mut compressed_size, &mut local_offset] {
+                        if *field == 0xFFFF_FFFF {
+                            *field = u64_at(bytes, k)?;
+                            k += 8;
+                        }
+                    }
+                }
+                j += 4 + len;
+            }
+            i = add(extra_end, comment_len)?;
+
+            let size = to_usize(size)?;
+            if size > remaining {
+                return Err(format!("{name} is too large to decompress"));
+            }
+            remaining -= size;
+            let local = to_usize(local_offset)?;
+            if u32_at(bytes, local)? != LOCAL_HEADER {
+                return Err("Invalid npz archive".into());
+            }
+            let data_start = add(
+                add(local, 30)?,
+                u16_at(bytes, add(local, 26)?)? as usize + u16_at(bytes, add(local, 28)?)? as usize,
+            )?;
+            let data = slice_at(bytes, data_start, to_usize(compressed_size)?)?;
+            let data = match method {
+                0 => data.to_vec(),
+                // Decompress no more than the declared size so that small archives
+                // cannot expand without bound
+                8 => miniz_oxide::inflate::decompress_to_vec_with_limit(data, size)
+                    .map_err(|e| format!("Failed to decompress {name}: {e:?}"))?,
+                _ => return Err(format!("{name} uses an unsupported compression method")),
+            };
+            if data.len() != size || crc32fast::hash(&data) != crc {
+                return Err(format!("{name} is corrupted"));
+            }
+            entries.push((name, data));
+        }
+        Ok(entries)
+    }
+
+    pub fn write_entries(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        let mut central = Vec::new();
+        for (name, data) in entries {
+            let offset = u32::try_from(bytes.len()).map_err(|_| "npz archive is too large")?;
+            let size = u32::try_from(data.len()).map_err(|_| "npz archive is too large")?;
+            let crc = crc32fast::hash(data);
+            // Fields shared by the local and central headers:
+            // version needed, flags, method, time, date, crc, sizes, name length, extra length
+            let mut common = Vec::new();
+            common.extend(20u16.to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+            common.extend(0x21u16.to_le_bytes());
+            common.extend(crc.to_le_bytes());
+            common.extend(size.to_le_bytes());
+            common.extend(size.to_le_bytes());
+            common.extend((name.len() as u16).to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+
+            bytes.extend(LOCAL_HEADER.to_le_bytes());
+            bytes.extend(&common);
+            bytes.extend(name.as_bytes());
+            bytes.extend(data);
+
+            central.extend(CENTRAL_HEADER.to_le_bytes());
+            central.extend(20u16.to_le_bytes());
+            central.extend(&common);
+            // Comment length, disk, internal and external attributes
+            central.extend([0; 10]);
+            central.extend(offset.to_le_bytes());
+            central.extend(name.as_bytes());
+        }
+        let cd_offset = u32::try_from(bytes.len()).map_err(|_| "npz archive is too large")?;
+        let count = u16::try_from(entries.len()).map_err(|_| "npz archive has too many arrays")?;
+        bytes.extend(&central);
+        bytes.extend(END_OF_CENTRAL_DIR.to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend(count.to_le_bytes());
+        bytes.extend(count.to_le_bytes());
+        bytes.extend((central.len() as u32).to_le_bytes());
+        bytes.extend(cd_offset.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn npy_with_header(header: &str) -> Vec<u8> {
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn npy_huge_shape() {
+        for shape in ["(4611686018427387904, 4)", "(2305843009213693952,)"] {
+            let header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': {shape}, }}");
+            assert!(Value::from_npy_bytes(&npy_with_header(&header)).is_err());
+        }
+    }
+
+    #[test]
+    fn npz_decompressed_size() {
+        let data = vec![0; 1 << 16];
+        let stored = zip::write_entries(&[("a.npy".into(), data.clone())]).unwrap();
+        // Replace the stored data with compressed data that inflates past its declared size
+        let compressed = miniz_oxide::deflate::compress_to_vec(&data, 6);
+        let name_len = "a.npy".len();
+        let header_len = 30 + name_len;
+        let mut bytes = stored[..header_len].to_vec();
+        bytes.extend(&compressed);
+        let central_start = bytes.len();
+        bytes.extend(&stored[header_len + data.len()..]);
+        for (offset, value) in [
+            // Local header method and sizes
+            (8, 8u32.to_le_bytes()[..2].to_vec()),
+            (18, (compressed.len() as u32).to_le_bytes().to_vec()),
+            (22, 100u32.to_le_bytes().to_vec()),
+            // Central header method and sizes
+            (central_start + 10, 8u32.to_le_bytes()[..2].to_vec()),
+            (
+                central_start + 20,
+                (compressed.len() as u32).to_le_bytes().to_vec(),
+            ),
+            (central_start + 24, 100u32.to_le_bytes().to_vec()),
+        ] {
+            bytes[offset..offset + value.len()].copy_from_slice(&value);
+        }
+        // The end of central directory points at the old central directory
+        let eocd = bytes.len() - 22;
+        bytes[eocd + 16..eocd + 20].copy_from_slice(&(central_start as u32).to_le_bytes());
+        let error = npz_bytes_to_values(&bytes).unwrap_err();
+        assert!(error.contains("Failed to decompress"), "{error}");
+        // The memory limit applies to the declared size
+        let value = Value::from(Array::<u8>::from(data.as_slice()));
+        let npz = values_to_npz_bytes([("a", &value)]).unwrap();
+        let error = npz_bytes_to_table(&npz, 100).unwrap_err();
+        assert!(error.contains("too large"), "{error}");
+        assert!(npz_bytes_to_table(&npz, 1 << 20).is_ok());
+    }
+}
@@ -277,24 +277,9 @@ impl Parser {
                 self.validate_words(&words, false)
             }
             // Check for uncapitalized binding names
-            if name.value.trim_end_matches('!').chars().count() >= 2
-                && name.value.chars().next().unwrap().is_ascii_lowercase()
-            {
-                let captialized: String = name
-                    .value
-                    .chars()
-                    .next()
-                    .map(|c| c.to_ascii_uppercase())
-                    .into_iter()
-                    .chain(name.value.chars().skip(1))
-                    .collect();
+            if let Some(advice) = binding_name_case_advice(&name.value) {
                 self.diagnostics.push(Diagnostic::new(
-                    format!(
-                        "Binding names with 2 or more characters should be TitleCase \
-                        to avoid collisions with future builtin functions.\n\
-                        Try `{}` instead of `{}`",
-                        captialized, name.value
-                    ),
+                    advice,
                     name.span.clone(),
                     DiagnosticKind::Advice,
                 ));
@@ -829,6 +814,39 @@ pub(crate) fn ident_modifier_args(ident: &Ident) -> u8 {
     count
 }
 
+/// Get advice for a binding name that should be TitleCase
+pub(crate) fn binding_name_case_advice(name: &str) -> Option<String> {
+    if name.trim_end_matches('!').chars().count() < 2
+        || !name.starts_with(|c: char| c.is_ascii_lowercase())
+    {
+        return None;
+    }
+    let capitalized: String = (name.chars().next())
+        .map(|c| c.to_ascii_uppercase())
+        .into_iter()
+        .chain(name.chars().skip(1))
+        .collect();
+    Some(format!(
+        "Binding names with 2 or more characters should be TitleCase \
+        to avoid collisions with future builtin functions.\n\
+        Try `{capitalized}` instead of `{name}`"
+    ))
+}
+
+/// Get the problem with a binding name whose `!`s do not match
+/// the number of modifier arguments its body references
+pub(crate) fn binding_name_modifier_args_problem(name: &str, referenced: usize) -> Option<String> {
+    let implied = ident_modifier_args(&name.into()) as usize;
+    if implied == referenced {
+        return None;
+    }
+    let this = format!("{}{}", name.trim_end_matches('!'), "!".repeat(referenced));
+    Some(format!(
+        "The name {name} implies {implied} modifier arguments, \
+        but the binding body references {referenced}. Try `{this}`."
+    ))
+}
+
 pub(crate) fn count_placeholders(words: &[Sp<Word>]) -> usize {
     let mut count = 0;
     for word in words {
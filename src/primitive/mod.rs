@@ -608,12 +608,7 @@ impl Primitive {
                     return Err(UiuaError::Throw(msg.into(), env.span().clone()));
                 }
             }
-            Primitive::Rand => {
-                thread_local! {
-                    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::seed_from_u64(instant::now().to_bits()));
-                }
-                env.push(RNG.with(|rng| rng.borrow_mut().gen::<f64>()));
-            }
+            Primitive::Rand => env.push(env.backend.rand()),
             Primitive::Gen => {
                 let seed = env.pop(1)?;
                 let mut rng =
@@ -660,7 +655,7 @@ impl Primitive {
                 let id = env.pop(1)?;
                 env.try_recv(id)?;
             }
-            Primitive::Now => env.push(instant::now() / 1000.0),
+            Primitive::Now => env.push(env.backend.now()),
             Primitive::Rectify => {
                 let f = env.pop_function()?;
                 env.call(f)?;
@@ -2,11 +2,14 @@ use std::{
     collections::{BTreeSet, HashMap},
     fs,
     hash::Hash,
-    mem::{replace, take},
+    mem::{replace, size_of, take},
     panic::{catch_unwind, AssertUnwindSafe},
     path::{Path, PathBuf},
     str::FromStr,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
@@ -16,9 +19,10 @@ use parking_lot::Mutex;
 use rand::prelude::*;
 
 use crate::{
-    algorithm, array::Array, boxed::Boxed, constants, function::*, lex::Span, parse::parse,
-    primitive::Primitive, value::Value, Diagnostic, DiagnosticKind, Ident, NativeSys, SysBackend,
-    SysOp, TraceFrame, UiuaError, UiuaResult,
+    algorithm, array::Array, ast::Item, boxed::Boxed, constants, function::*, lex::Span,
+    parse::parse, primitive::Primitive, value::Value, DebugAction, DebugState, Debugger,
+    Diagnostic, DiagnosticKind, Ident, NativeSys, SysBackend, SysOp, TraceFrame, UiuaError,
+    UiuaResult,
 };
 
 /// The Uiua runtime
@@ -35,7 +39,7 @@ pub struct Uiua {
     /// The thread's function stack
     pub(crate) function_stack: Vec<Arc<Function>>,
     /// The thread's temp stack for inlining
-    temp_stacks: [Vec<Value>; TempStack::CARDINALITY],
+    pub(crate) temp_stacks: [Vec<Value>; TempStack::CARDINALITY],
     /// The thread's temp stack for functions
     temp_function_stack: Vec<Arc<Function>>,
     /// The current scope
@@ -44,12 +48,20 @@ pub struct Uiua {
     pub(crate) higher_scopes: Vec<Scope>,
     /// Determines which How test scopes are run
     pub(crate) mode: RunMode,
+    /// Whether to compile code without running it, except for imports
+    pub(crate) compile_only: bool,
     /// A limit on the execution duration in milliseconds
     execution_limit: Option<f64>,
     /// The time at which execution started
-    execution_start: f64,
+    pub(crate) execution_start: f64,
+    /// Set when the program should stop
+    interrupted: Arc<AtomicBool>,
+    /// A limit on the size in bytes of the arrays in use
+    memory_limit: Option<usize>,
+    /// A limit on the number of elements in each array
+    max_elements: Option<usize>,
     /// The paths of files currently being imported (used to detect import cycles)
-    current_imports: Arc<Mutex<Vec<PathBuf>>>,
+    pub(crate) current_imports: Arc<Mutex<Vec<PathBuf>>>,
     /// The bindings of imported files
     imports: Arc<Mutex<HashMap<PathBuf, HashMap<Ident, usize>>>>,
     /// Accumulated diagnostics
@@ -66,10 +78,51 @@ pub struct Uiua {
     cli_file_path: PathBuf,
     /// The system backend
     pub(crate) backend: Arc<dyn SysBackend>,
+    /// The debugger hook
+    debugger: Option<Arc<Mutex<dyn Debugger>>>,
     /// The thread interface
     thread: ThisThread,
 }
 
+/// A handle for interrupting a running [`Uiua`] from another thread
+///
+/// Interruption is checked between instructions, so a long-running primitive
+/// finishes before the program stops. Threads spawned by the program are
+/// interrupted too.
+///
+/// ```
+/// use std::{thread, time::Duration};
+/// use uiua::*;
+///
+/// let mut env = Uiua::with_native_sys();
+/// let handle = env.interrupt_handle();
+/// thread::spawn(move || {
+///     thread::sleep(Duration::from_millis(100));
+///     handle.interrupt();
+/// });
+/// let err = env.load_str("⍢(+1)(1) 0").unwrap_err();
+/// assert!(err.is_interrupted());
+/// ```
+#[derive(Debug, Clone)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    /// Stop the program at the next instruction
+    ///
+    /// The program stays interrupted until [`InterruptHandle::reset`] is called.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    /// Check whether the program has been interrupted
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    /// Allow the program to run again
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
 #[derive(Clone)]
 pub(crate) enum Global {
     Val(Value),
@@ -84,7 +137,7 @@ pub(crate) struct Scope {
     /// The stack height at the start of each array currently being built
     array: Vec<usize>,
     /// The call stack
-    call: Vec<StackFrame>,
+    pub(crate) call: Vec<StackFrame>,
     /// The recur stack
     with: Vec<usize>,
     /// Map local names to global indices
@@ -129,15 +182,15 @@ struct Fills {
 }
 
 #[derive(Clone)]
-struct StackFrame {
+pub(crate) struct StackFrame {
     /// The function being executed
-    function: Arc<Function>,
+    pub(crate) function: Arc<Function>,
     /// The span at which the function was called
-    call_span: usize,
+    pub(crate) call_span: usize,
     /// The program counter for the function
-    pc: usize,
+    pub(crate) pc: usize,
     /// Additional spans for error reporting
-    spans: Vec<(usize, Option<Primitive>)>,
+    pub(crate) spans: Vec<(usize, Option<Primitive>)>,
 }
 
 #[derive(Debug, Clone)]
@@ -226,6 +279,7 @@ impl Uiua {
             current_imports: Arc::new(Mutex::new(Vec::new())),
             imports: Arc::new(Mutex::new(HashMap::new())),
             mode: RunMode::Normal,
+            compile_only: false,
             diagnostics: BTreeSet::new(),
             backend: Arc::new(NativeSys),
             print_diagnostics: false,
@@ -235,6 +289,10 @@ impl Uiua {
             cli_file_path: PathBuf::new(),
             execution_limit: None,
             execution_start: 0.0,
+            interrupted: Arc::new(AtomicBool::new(false)),
+            memory_limit: None,
+            max_elements: None,
+            debugger: None,
             thread: ThisThread::default(),
         }
     }
@@ -279,6 +337,60 @@ impl Uiua {
         self.execution_limit = Some(limit.as_millis() as f64);
         self
     }
+    /// Limit the size in bytes of the arrays the program holds
+    ///
+    /// The arrays on the stack are counted together with each new array, and loops like
+    /// [`each`](Primitive::Each) count the results they collect. Primitives that can create
+    /// very large arrays from small inputs, like
+    /// [`reshape`](Primitive::Reshape), [`range`](Primitive::Range), and filled
+    /// [`take`](Primitive::Take), fail with an error before allocating an array that would
+    /// exceed the limit.
+    ///
+    /// ```
+    /// use uiua::*;
+    ///
+    /// let limited = || Uiua::with_native_sys().with_memory_limit(1 << 20);
+    /// assert!(limited().load_str("⇡1e9").is_err());
+    /// assert!(limited().load_str("↯1e5_1e5 0").is_err());
+    /// assert!(limited().load_str("⊞+.⇡1e5").is_err());
+    /// assert!(limited().load_str("⬚0↙1e8 [1]").is_err());
+    /// assert!(limited().load_str("⍥(⇡1e4)1e4").is_err());
+    /// assert!(limited().load_str("≡(⇡1e4)⇡1e4").is_err());
+    /// assert!(limited().load_str("∵(⇡1e4)⇡1e4").is_err());
+    /// let mut env = limited();
+    /// env.load_str("⍣(⇡1e9)(⋅\"too big\")").unwrap();
+    /// assert_eq!(env.pop_string().unwrap(), "too big");
+    /// env.load_str("⇡1e3").unwrap();
+    /// ```
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+    /// Limit the number of elements in each array the program creates
+    ///
+    /// This is checked in the same places as [`Uiua::with_memory_limit`].
+    ///
+    /// ```
+    /// use uiua::*;
+    ///
+    /// let mut env = Uiua::with_native_sys().with_max_elements(100);
+    /// assert!(env.load_str("▽1000 5").is_err());
+    /// assert!(env.load_str("◫2 ⇡60").is_err());
+    /// env.load_str("◫2 ⇡50").unwrap();
+    /// ```
+    pub fn with_max_elements(mut self, count: usize) -> Self {
+        self.max_elements = Some(count);
+        self
+    }
+    /// Get a handle that can interrupt the program from another thread
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle(self.interrupted.clone())
+    }
+    /// Set a [`Debugger`] to be called before each instruction
+    pub fn with_debugger(mut self, debugger: impl Debugger) -> Self {
+        self.debugger = Some(Arc::new(Mutex::new(debugger)));
+        self
+    }
     /// Set the [`RunMode`]
     ///
     /// Default is [`RunMode::Normal`]
@@ -382,6 +494,43 @@ code:
         }
         res
     }
+    /// Run code one top-level item at a time, continuing after errors
+    ///
+    /// `on_item` is called with each item and its result.
+    /// Only parse errors are returned.
+    pub(crate) fn check_str(
+        &mut self,
+        input: &str,
+        path: Option<&Path>,
+        mut on_item: impl FnMut(&mut Self, &Item, UiuaResult),
+    ) -> UiuaResult {
+        let (items, errors, diagnostics) = parse(input, path);
+        self.diagnostics.extend(diagnostics);
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        if let Some(path) = path {
+            self.current_imports.lock().push(path.into());
+        }
+        for item in items {
+            self.execution_start = instant::now();
+            let res = catch_unwind(AssertUnwindSafe(|| self.items(vec![item.clone()], false)))
+                .unwrap_or_else(|_| Err(self.error("The interpreter has crashed!")));
+            on_item(self, &item, res);
+        }
+        if path.is_some() {
+            self.current_imports.lock().pop();
+        }
+        Ok(())
+    }
+    /// Get the signature of a bound name
+    pub fn binding_signature(&self, name: &str) -> Option<Signature> {
+        let idx = *self.scope.names.get(name)?;
+        Some(match &self.globals.lock()[idx] {
+            Global::Val(_) => Signature::new(0, 1),
+            Global::Func { f, .. } => f.signature(),
+        })
+    }
     fn trace_error(&self, mut error: UiuaError, frame: StackFrame) -> UiuaError {
         let mut frames = Vec::new();
         for (span, prim) in &frame.spans {
@@ -487,6 +636,14 @@ code:
             // println!();
             // println!("  {:?}", instr);
 
+            if let Some(debugger) = &self.debugger {
+                let state = DebugState { env: self, instr };
+                if debugger.lock().before_instr(&state) == DebugAction::Abort {
+                    let err = self.error("Program was stopped by the debugger");
+                    let frame = self.scope.call.pop().unwrap();
+                    return Err(self.trace_error(err, frame));
+                }
+            }
             if self.time_instrs {
                 formatted_instr = format!("{instr:?}");
                 self.last_time = instant::now();
@@ -670,6 +827,9 @@ code:
                         return Err(UiuaError::Timeout(self.span()));
                     }
                 }
+                if self.interrupted.load(Ordering::Relaxed) {
+                    return Err(UiuaError::Interrupted(self.span()));
+                }
             }
         })
     }
@@ -745,6 +905,73 @@ code:
         spans.push(span.into());
         idx
     }
+    /// Check that an array of `T` with the given dimensions fits in the memory limits
+    pub(crate) fn check_alloc<T>(&self, dims: impl IntoIterator<Item = usize>) -> UiuaResult {
+        self.check_alloc_bytes(dims, size_of::<T>())
+    }
+    /// Check that an array with the given dimensions and element size fits in the memory limits
+    ///
+    /// The memory limit applies to the new array together with the arrays already on the stack.
+    pub(crate) fn check_alloc_bytes(
+        &self,
+        dims: impl IntoIterator<Item = usize>,
+        element_size: usize,
+    ) -> UiuaResult {
+        if self.memory_limit.is_none() && self.max_elements.is_none() {
+            return Ok(());
+        }
+        let elements = (dims.into_iter()).fold(1usize, |acc, dim| acc.saturating_mul(dim));
+        if let Some(max) = self.max_elements {
+            if elements > max {
+                return Err(self.error(format!(
+                    "Creating an array of {elements} elements would exceed \
+                    the maximum of {max} elements"
+                )));
+            }
+        }
+        if let Some(limit) = self.memory_limit {
+            let bytes = elements.saturating_mul(element_size);
+            let in_use = self.memory_in_use();
+            if bytes.saturating_add(in_use) > limit {
+                return Err(self.error(format!(
+                    "Creating an array of {elements} elements ({bytes} bytes) \
+                    would exceed the memory limit of {limit} bytes \
+                    with {in_use} bytes already in use"
+                )));
+            }
+        }
+        Ok(())
+    }
+    /// Check that arrays using the given number of bytes fit in the memory limit
+    ///
+    /// Loops that collect their results get [`Uiua::memory_in_use`] once and add the size
+    /// of the values they hold, so the stacks are not walked for every iteration.
+    pub(crate) fn check_memory_in_use(&self, in_use: usize) -> UiuaResult {
+        let Some(limit) = self.memory_limit else {
+            return Ok(());
+        };
+        if in_use > limit {
+            return Err(self.error(format!(
+                "Arrays using {in_use} bytes would exceed the memory limit of {limit} bytes"
+            )));
+        }
+        Ok(())
+    }
+    /// Get the number of bytes that can be allocated before reaching the memory limit
+    pub(crate) fn memory_available(&self) -> Option<usize> {
+        (self.memory_limit).map(|limit| limit.saturating_sub(self.memory_in_use()))
+    }
+    /// Get the number of bytes used by the arrays on the stacks
+    ///
+    /// This is always `0` when there is no memory limit.
+    pub(crate) fn memory_in_use(&self) -> usize {
+        if self.memory_limit.is_none() {
+            return 0;
+        }
+        (self.stack.iter())
+            .chain(self.temp_stacks.iter().flatten())
+            .fold(0, |acc, val| acc.saturating_add(val.byte_size()))
+    }
     /// Construct an error with the current span
     pub fn error(&self, message: impl ToString) -> UiuaError {
         UiuaError::Run(self.span().clone().sp(message.to_string()))
@@ -876,6 +1103,10 @@ code:
         let function = self.create_function(signature, f);
         self.bind_function(name, function)
     }
+    /// Get the entire stack
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
     /// Take the entire stack
     pub fn take_stack(&mut self) -> Vec<Value> {
         for stack in &mut self.temp_stacks {
@@ -917,6 +1148,13 @@ code:
         }
         bindings
     }
+    /// Get the names of all bindings in the current scope, not including shadowable constants
+    pub fn bound_names(&self) -> Vec<Ident> {
+        (self.scope.names.iter())
+            .filter(|(_, idx)| **idx >= constants().len())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
     /// Get all diagnostics
     pub fn diagnostics(&self) -> &BTreeSet<Diagnostic> {
         &self.diagnostics
@@ -1156,6 +1394,7 @@ code:
             scope: self.scope.clone(),
             higher_scopes: self.higher_scopes.last().cloned().into_iter().collect(),
             mode: self.mode,
+            compile_only: self.compile_only,
             current_imports: self.current_imports.clone(),
             imports: self.imports.clone(),
             diagnostics: BTreeSet::new(),
@@ -1167,6 +1406,10 @@ code:
             backend: self.backend.clone(),
             execution_limit: self.execution_limit,
             execution_start: self.execution_start,
+            interrupted: self.interrupted.clone(),
+            memory_limit: self.memory_limit,
+            max_elements: self.max_elements,
+            debugger: None,
             thread,
         };
         #[cfg(not(target_arch = "wasm32"))]
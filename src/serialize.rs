@@ -0,0 +1,291 @@
+//! Serde implementations for [`Value`] and [`Array`]
+//!
+//! Human-readable formats get arrays as `{"shape": [...], "data": [...]}`,
+//! while compact formats get them as a `(shape, data)` tuple.
+//! Character data is always written as a string, and byte data is written
+//! as raw bytes in compact formats.
+//! Numbers that are not finite are written as `"∞"`, `"¯∞"`, or `"NaN"`
+//! in human-readable formats, because formats like JSON cannot represent them.
+
+use std::fmt;
+
+use ecow::EcoVec;
+use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Array, ArrayValue, Boxed, Complex, Shape, Value};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Array")]
+struct ArrayRepr<S, D> {
+    shape: S,
+    data: D,
+}
+
+fn serialize_array<S: Serializer>(
+    shape: &[usize],
+    data: impl Serialize,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        ArrayRepr { shape, data }.serialize(serializer)
+    } else {
+        (shape, data).serialize(serializer)
+    }
+}
+
+fn deserialize_array<'de, T, D, De>(
+    deserializer: De,
+    into_data: impl FnOnce(D) -> EcoVec<T>,
+) -> Result<Array<T>, De::Error>
+where
+    T: ArrayValue,
+    D: Deserialize<'de>,
+    De: Deserializer<'de>,
+{
+    let (shape, data): (Vec<usize>, D) = if deserializer.is_human_readable() {
+        let repr = ArrayRepr::<Vec<usize>, D>::deserialize(deserializer)?;
+        (repr.shape, repr.data)
+    } else {
+        Deserialize::deserialize(deserializer)?
+    };
+    let data = into_data(data);
+    let elements = (shape.iter())
+        .try_fold(1usize, |acc, &dim| acc.checked_mul(dim))
+        .ok_or_else(|| de::Error::custom("array shape is too large"))?;
+    if elements != data.len() {
+        return Err(de::Error::custom(format!(
+            "array shape {shape:?} has {elements} elements, but there are {} elements of data",
+            data.len()
+        )));
+    }
+    Ok(Array::new(shape.into_iter().collect::<Shape>(), data))
+}
+
+macro_rules! array_serde {
+    ($ty:ty, $data:ty, |$ser:ident| $to_data:expr, |$de:ident| $from_data:expr) => {
+        impl Serialize for Array<$ty> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let $ser = &*self.data;
+                serialize_array(&self.shape, $to_data, serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Array<$ty> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize_array(deserializer, |$de: $data| $from_data)
+            }
+        }
+    };
+}
+
+array_serde!(
+    f64,
+    Vec<Num>,
+    |data| data.iter().map(|&n| Num(n)).collect::<Vec<_>>(),
+    |data| data.into_iter().map(|n| n.0).collect()
+);
+array_serde!(Complex, Vec<Complex>, |data| data, |data| data.into());
+array_serde!(Boxed, Vec<Boxed>, |data| data, |data| data.into());
+array_serde!(
+    char,
+    String,
+    |data| data.iter().collect::<String>(),
+    |data| data.chars().collect()
+);
+array_serde!(u8, ByteData, |data| ByteData(data.into()), |data| data
+    .0
+    .into());
+
+/// A number that is written as a string in human-readable formats if it is not finite
+struct Num(f64);
+
+impl Serialize for Num {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        num::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Num {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        num::deserialize(deserializer).map(Num)
+    }
+}
+
+/// Serde functions for numbers that are written as strings in human-readable formats
+/// if they are not finite
+pub(crate) mod num {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(n: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() || n.is_finite() {
+            serializer.serialize_f64(*n)
+        } else if n.is_nan() {
+            serializer.serialize_str("NaN")
+        } else if *n > 0.0 {
+            serializer.serialize_str("∞")
+        } else {
+            serializer.serialize_str("¯∞")
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        struct NumVisitor;
+        impl<'de> de::Visitor<'de> for NumVisitor {
+            type Value = f64;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number, \"∞\", \"¯∞\", or \"NaN\"")
+            }
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(v)
+            }
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(v as f64)
+            }
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(v as f64)
+            }
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                match v {
+                    "∞" => Ok(f64::INFINITY),
+                    "¯∞" => Ok(f64::NEG_INFINITY),
+                    "NaN" => Ok(f64::NAN),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(NumVisitor)
+        } else {
+            f64::deserialize(deserializer)
+        }
+    }
+}
+
+/// Byte data that is written as raw bytes in compact formats
+struct ByteData(Vec<u8>);
+
+impl Serialize for ByteData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+            for byte in &self.0 {
+                seq.serialize_element(byte)?;
+            }
+            seq.end()
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ByteVisitor;
+        impl<'de> de::Visitor<'de> for ByteVisitor {
+            type Value = ByteData;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "bytes")
+            }
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(ByteData(v.into()))
+            }
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(ByteData(v))
+            }
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(ByteData(bytes))
+            }
+        }
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_seq(ByteVisitor)
+        } else {
+            deserializer.deserialize_bytes(ByteVisitor)
+        }
+    }
+}
+
+/// The variants of [`Value`], in an order that does not depend on enabled features
+#[derive(Deserialize)]
+#[serde(rename = "Value", rename_all = "snake_case")]
+enum ValueRepr {
+    Num(Array<f64>),
+    Byte(Array<u8>),
+    Complex(Array<Complex>),
+    Char(Array<char>),
+    Box(Array<Boxed>),
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Num(arr) => serializer.serialize_newtype_variant("Value", 0, "num", arr),
+            #[cfg(feature = "bytes")]
+            Value::Byte(arr) => serializer.serialize_newtype_variant("Value", 1, "byte", arr),
+            Value::Complex(arr) => serializer.serialize_newtype_variant("Value", 2, "complex", arr),
+            Value::Char(arr) => serializer.serialize_newtype_variant("Value", 3, "char", arr),
+            Value::Box(arr) => serializer.serialize_newtype_variant("Value", 4, "box", arr),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match ValueRepr::deserialize(deserializer)? {
+            ValueRepr::Num(arr) => arr.into(),
+            ValueRepr::Byte(arr) => arr.into(),
+            ValueRepr::Complex(arr) => arr.into(),
+            ValueRepr::Char(arr) => arr.into(),
+            ValueRepr::Box(arr) => arr.into(),
+        })
+    }
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn value_serde_roundtrip() {
+    use crate::Uiua;
+
+    let mut env = Uiua::with_native_sys();
+    env.load_str(r#"↯2_3⇡6 "hello" {1 "two" [3 4]} ℂ1 2 [] ↯0_2_0 @a [1 ∞ ¯∞ NaN] ℂ∞ NaN"#)
+        .unwrap();
+    env.push(Array::<u8>::new(
+        Shape::from([2, 2].as_slice()),
+        [1u8, 2, 3, 4],
+    ));
+    for value in env.take_stack() {
+        let json = serde_json::to_string(&value).unwrap();
+        let from_json: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value, from_json, "{json}");
+        assert_eq!(value.type_name(), from_json.type_name(), "{json}");
+    }
+    let json = serde_json::to_string(&Value::from("hi")).unwrap();
+    assert_eq!(json, r#"{"char":{"shape":[2],"data":"hi"}}"#);
+    let json =
+        serde_json::to_string(&Value::from(EcoVec::from([1.0, f64::INFINITY, f64::NAN]))).unwrap();
+    assert_eq!(json, r#"{"num":{"shape":[3],"data":[1.0,"∞","NaN"]}}"#);
+    let err = serde_json::from_str::<Value>(r#"{"num":{"shape":[2,2],"data":[1,2,3]}}"#);
+    assert!(err.is_err());
+}
+
+#[test]
+fn value_compact_serde_roundtrip() {
+    use crate::Uiua;
+
+    let mut env = Uiua::with_native_sys();
+    env.load_str(r#"↯2_3⇡6 "hello" {1 "two" [3 4]} ℂ1 2 [] ↯0_2_0 @a [1 ∞ ¯∞ NaN] ℂ∞ NaN"#)
+        .unwrap();
+    env.push(Array::<u8>::new(
+        Shape::from([2, 2].as_slice()),
+        [1u8, 2, 3, 4],
+    ));
+    for value in env.take_stack() {
+        let bytes = bincode::serialize(&value).unwrap();
+        let from_bytes: Value = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(value, from_bytes);
+        assert_eq!(value.type_name(), from_bytes.type_name());
+    }
+}
@@ -1,7 +1,9 @@
 use std::{
     any::Any,
+    cell::{Cell, RefCell},
     collections::{HashMap, HashSet},
-    io::{stderr, stdin, Cursor, Read, Write},
+    io::{stderr, Cursor, Write},
+    mem::take,
     path::Path,
     sync::OnceLock,
     time::Duration,
@@ -13,13 +15,16 @@ use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
 use image::{DynamicImage, ImageOutputFormat};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use rand::prelude::*;
 use tinyvec::tiny_vec;
 
 use crate::{
-    array::{Array, Shape},
+    algorithm::monadic::parse_number,
+    array::{Array, FormatShape, Shape},
     boxed::Boxed,
     cowslice::{cowslice, CowSlice},
     function::Signature,
+    npy::{npz_bytes_to_table, table_to_npz_bytes},
     primitive::PrimDoc,
     value::Value,
     Uiua, UiuaError, UiuaResult,
@@ -132,6 +137,7 @@ pub enum SysOpClass {
     Images,
     Gifs,
     Tcp,
+    Data,
     Misc,
 }
 
@@ -274,6 +280,115 @@ sys_op! {
     ///
     /// Expects a path and a [rank]`1` array or either numbers or characters.
     (2(0), FWriteAll, Filesystem, "&fwa", "file - write all"),
+    /// Decode a JSON string into a value
+    ///
+    /// Numbers and booleans become numbers, and `null` becomes `NaN`.
+    /// Strings become character arrays.
+    /// Arrays whose items are all numbers or uniformly-shaped numeric arrays become numeric arrays.
+    /// Other arrays become lists of boxes.
+    /// Objects become rank `2` box arrays with a row for each key-value pair.
+    ///
+    /// ex: &jsond "[1, 2, 3]"
+    /// ex: &jsond "[[1, 2], [3, 4]]"
+    /// ex: &jsond "{\"name\": \"Uiua\", \"tags\": [\"array\", \"stack\"]}"
+    ///
+    /// [under] [&jsond] can be used to modify JSON.
+    /// ex: ⍜&jsond(×2) "[1, 2, 3]"
+    ///
+    /// See also: [&jsone]
+    (1, JsonDecode, Data, "&jsond", "json - decode"),
+    /// Encode a value as a JSON string
+    ///
+    /// This is the inverse of [&jsond].
+    /// Numeric arrays become nested JSON arrays of numbers.
+    /// JSON cannot represent [infinity] or NaN, so encoding them is an error.
+    /// Character arrays become strings, or nested arrays of strings.
+    /// Rank `2` box arrays with `2` columns whose first column is all strings become objects.
+    /// Other box arrays become nested arrays of their contents.
+    ///
+    /// ex: &jsone [1_2 3_4]
+    /// ex: &jsone {"hello" 5 [1 2 3]}
+    ///
+    /// See also: [&jsond]
+    (1, JsonEncode, Data, "&jsone", "json - encode"),
+    /// Decode CSV data into a table of strings
+    ///
+    /// The first argument is the delimiter, which must be a single character.
+    /// Use `","` for CSV and `"\t"` for TSV.
+    /// The second argument is the CSV data.
+    ///
+    /// Returns a rank `2` box array of strings with a row for each record.
+    /// Fields may be quoted, and quotes in quoted fields are escaped by doubling them.
+    /// Records may be separated by `\n` or `\r\n`, and blank lines are skipped.
+    /// Records with fewer fields than the longest record are padded with empty strings.
+    ///
+    /// ex: &csvd "," "a,b\n1,2\n3,\"x,y\"\n"
+    ///
+    /// See also: [&csvn] [&csve]
+    (2, CsvDecode, Data, "&csvd", "csv - decode"),
+    /// Decode CSV data into a table, parsing numeric columns
+    ///
+    /// This works like [&csvd], except that columns whose values can all be [parse]d are converted to numbers.
+    /// If every value in a column but the first can be parsed, the first is kept as a header.
+    /// Empty fields in numeric columns become `NaN`.
+    ///
+    /// ex: &csvn "," "name,age\nAlice,30\nBob,25\n"
+    /// ex: &csvn "\t" "1\t2\n3\t4\n"
+    ///
+    /// See also: [&csvd] [&csve]
+    (2, CsvDecodeNums, Data, "&csvn", "csv - decode with numbers"),
+    /// Encode a table as CSV
+    ///
+    /// The first argument is the delimiter, which must be a single character.
+    /// Use `","` for CSV and `"\t"` for TSV.
+    /// The second argument is the table.
+    ///
+    /// The table may be a rank `2` numeric array or a rank `2` box array of strings and numbers.
+    /// A rank `1` array is encoded as a single record.
+    /// Each row of a rank `2` character array is encoded as a record with a single field.
+    /// A rank `3` character array is a table of strings of the same length.
+    /// Fields that contain the delimiter, quotes, or newlines are quoted.
+    ///
+    /// ex: &csve "," [1_2 3_4]
+    /// ex: &csve "," ↯2_2{"name" "age" "Alice" 30}
+    ///
+    /// See also: [&csvd] [&csvn]
+    (2, CsvEncode, Data, "&csve", "csv - encode"),
+    /// Decode a NumPy `.npy` file from a byte array
+    ///
+    /// Float, integer, bool, and complex dtypes are supported.
+    /// The array's shape is preserved.
+    ///
+    /// ex: &npyd &npye [1_2 3_4]
+    ///
+    /// See also: [&npye] [&npzd]
+    (1, NpyDecode, Data, "&npyd", "npy - decode"),
+    /// Encode an array as a NumPy `.npy` file
+    ///
+    /// Numeric arrays are encoded with the `float64` dtype, and complex arrays with the `complex128` dtype.
+    ///
+    /// ex: △ &npye [1_2 3_4]
+    ///
+    /// See also: [&npyd] [&npze]
+    (1, NpyEncode, Data, "&npye", "npy - encode"),
+    /// Decode a NumPy `.npz` archive from a byte array
+    ///
+    /// Returns a rank `2` box array with a row for each name and array in the archive.
+    /// Both compressed and uncompressed archives are supported.
+    ///
+    /// ex: &npzd &npze {"a" [1 2 3] "b" ℂ1 2}
+    ///
+    /// See also: [&npze] [&npyd]
+    (1, NpzDecode, Data, "&npzd", "npz - decode"),
+    /// Encode arrays as an uncompressed NumPy `.npz` archive
+    ///
+    /// Expects a rank `2` box array with a row for each name and array.
+    /// A rank `1` box list of alternating names and arrays is also accepted.
+    ///
+    /// ex: &npze {"a" [1 2 3] "b" ℂ1 2}
+    ///
+    /// See also: [&npzd] [&npye]
+    (1, NpzEncode, Data, "&npze", "npz - encode"),
     /// Decode an image from a byte array
     ///
     /// Supported formats are `jpg`, `png`, `bmp`, `gif`, and `ico`.
@@ -449,10 +564,15 @@ sys_op! {
 ///
 /// Other handles can be used by files or sockets.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Handle(pub u64);
 
 impl Handle {
-    const STDIN: Self = Self(0);
+    /// The handle for stdin
+    ///
+    /// Reads from this handle are passed to [`SysBackend::read`] and [`SysBackend::read_until`].
+    pub const STDIN: Self = Self(0);
     const STDOUT: Self = Self(1);
     const STDERR: Self = Self(2);
     /// The first handle that can be used by the user
@@ -667,10 +787,50 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
         Err("Making HTTPS requests is not supported in this environment".into())
     }
+    /// Get the current time in seconds since the Unix epoch
+    ///
+    /// This is used by `now`.
+    fn now(&self) -> f64 {
+        instant::now() / 1000.0
+    }
+    /// Generate a random number in the range `[0, 1)`
+    ///
+    /// This is used by `random`.
+    fn rand(&self) -> f64 {
+        thread_local! {
+            static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::seed_from_u64(instant::now().to_bits()));
+        }
+        RNG.with(|rng| rng.borrow_mut().gen::<f64>())
+    }
+    /// Check whether a system function is allowed to run
+    ///
+    /// This is called before every system function is run.
+    fn check_sys_op(&self, op: SysOp) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// The system function being run on this thread
+    static CURRENT_SYS_OP: Cell<Option<SysOp>> = const { Cell::new(None) };
+}
+
+/// Get the system function being run on this thread, if any
+pub(crate) fn current_sys_op() -> Option<SysOp> {
+    CURRENT_SYS_OP.with(Cell::get)
 }
 
 impl SysOp {
     pub(crate) fn run(&self, env: &mut Uiua) -> UiuaResult {
+        /// Restores the outer system function when this one finishes, even if it panics
+        struct Restore(Option<SysOp>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_SYS_OP.with(|op| op.set(self.0));
+            }
+        }
+        let _restore = Restore(CURRENT_SYS_OP.with(|op| op.replace(Some(*self))));
+        env.backend.check_sys_op(*self).map_err(|e| env.error(e))?;
         match self {
             SysOp::Show => {
                 let s = env.pop(1)?.show();
@@ -757,12 +917,6 @@ impl SysOp {
                 let bytes = match handle {
                     Handle::STDOUT => return Err(env.error("Cannot read from stdout")),
                     Handle::STDERR => return Err(env.error("Cannot read from stderr")),
-                    Handle::STDIN => stdin()
-                        .lock()
-                        .bytes()
-                        .take(count)
-                        .collect::<Result<Vec<_>, _>>()
-                        .map_err(|e| env.error(e))?,
                     _ => env.backend.read(handle, count).map_err(|e| env.error(e))?,
                 };
                 let s = String::from_utf8(bytes).map_err(|e| env.error(e))?;
@@ -777,12 +931,6 @@ impl SysOp {
                 let bytes = match handle {
                     Handle::STDOUT => return Err(env.error("Cannot read from stdout")),
                     Handle::STDERR => return Err(env.error("Cannot read from stderr")),
-                    Handle::STDIN => stdin()
-                        .lock()
-                        .bytes()
-                        .take(count)
-                        .collect::<Result<Vec<_>, _>>()
-                        .map_err(|e| env.error(e))?,
                     _ => env.backend.read(handle, count).map_err(|e| env.error(e))?,
                 };
                 env.push(Array::from(bytes.as_slice()));
@@ -799,34 +947,6 @@ impl SysOp {
                 match handle {
                     Handle::STDOUT => return Err(env.error("Cannot read from stdout")),
                     Handle::STDERR => return Err(env.error("Cannot read from stderr")),
-                    Handle::STDIN => {
-                        let mut is_string = false;
-                        let delim_bytes: Vec<u8> = match delim {
-                            Value::Num(arr) => arr.data.iter().map(|&x| x as u8).collect(),
-                            #[cfg(feature = "bytes")]
-                            Value::Byte(arr) => arr.data.into(),
-                            Value::Char(arr) => {
-                                is_string = true;
-                                arr.data.iter().collect::<String>().into()
-                            }
-                            _ => return Err(env.error("Delimiter must be a string or byte array")),
-                        };
-                        let mut buffer = Vec::new();
-                        let stdin = stdin().lock();
-                        for byte in stdin.bytes() {
-                            let byte = byte.map_err(|e| env.error(e))?;
-                            buffer.push(byte);
-                            if buffer.ends_with(&delim_bytes) {
-                                break;
-                            }
-                        }
-                        if is_string {
-                            let s = String::from_utf8_lossy(&buffer).into_owned();
-                            env.push(s);
-                        } else {
-                            env.push(Array::from(buffer.as_slice()));
-                        }
-                    }
                     _ => match delim {
                         Value::Num(arr) => {
                             let delim: Vec<u8> = arr.data.iter().map(|&x| x as u8).collect();
@@ -851,7 +971,11 @@ impl SysOp {
                                 .backend
                                 .read_until(handle, &delim)
                                 .map_err(|e| env.error(e))?;
-                            let s = String::from_utf8(bytes).map_err(|e| env.error(e))?;
+                            let s = if handle == Handle::STDIN {
+                                String::from_utf8_lossy(&bytes).into_owned()
+                            } else {
+                                String::from_utf8(bytes).map_err(|e| env.error(e))?
+                            };
                             env.push(s);
                         }
                         _ => return Err(env.error("Delimiter must be a string or byte array")),
@@ -988,6 +1112,53 @@ impl SysOp {
                 let path = env.pop(1)?.as_string(env, "Invoke path must be a string")?;
                 env.backend.invoke(&path).map_err(|e| env.error(e))?;
             }
+            SysOp::JsonDecode => {
+                let json = env.pop(1)?.as_string(env, "JSON must be a string")?;
+                let value = json_to_value(&json).map_err(|e| env.error(e))?;
+                env.push(value);
+            }
+            SysOp::JsonEncode => {
+                let value = env.pop(1)?;
+                let json = value_to_json(&value).map_err(|e| env.error(e))?;
+                env.push(json);
+            }
+            SysOp::CsvDecode | SysOp::CsvDecodeNums => {
+                let delimiter = env.pop(1)?.as_string(env, "Delimiter must be a string")?;
+                let delimiter = csv_delimiter(&delimiter).map_err(|e| env.error(e))?;
+                let csv = env.pop(2)?.as_string(env, "CSV must be a string")?;
+                let parse_numbers = *self == SysOp::CsvDecodeNums;
+                let value =
+                    csv_to_value(&csv, delimiter, parse_numbers).map_err(|e| env.error(e))?;
+                env.push(value);
+            }
+            SysOp::CsvEncode => {
+                let delimiter = env.pop(1)?.as_string(env, "Delimiter must be a string")?;
+                let delimiter = csv_delimiter(&delimiter).map_err(|e| env.error(e))?;
+                let value = env.pop(2)?;
+                let csv = value_to_csv(&value, delimiter).map_err(|e| env.error(e))?;
+                env.push(csv);
+            }
+            SysOp::NpyDecode => {
+                let bytes = env.pop(1)?.as_bytes(env, "npy data must be a byte array")?;
+                let value = Value::from_npy_bytes(&bytes).map_err(|e| env.error(e))?;
+                env.push(value);
+            }
+            SysOp::NpyEncode => {
+                let value = env.pop(1)?;
+                let bytes = value.to_npy_bytes().map_err(|e| env.error(e))?;
+                env.push(Array::<u8>::from(bytes.as_slice()));
+            }
+            SysOp::NpzDecode => {
+                let bytes = env.pop(1)?.as_bytes(env, "npz data must be a byte array")?;
+                let limit = env.memory_available().unwrap_or(usize::MAX);
+                let value = npz_bytes_to_table(&bytes, limit).map_err(|e| env.error(e))?;
+                env.push(value);
+            }
+            SysOp::NpzEncode => {
+                let value = env.pop(1)?;
+                let bytes = table_to_npz_bytes(&value).map_err(|e| env.error(e))?;
+                env.push(Array::<u8>::from(bytes.as_slice()));
+            }
             SysOp::ImDecode => {
                 let bytes: CowSlice<u8> = match env.pop(1)? {
                     #[cfg(feature = "bytes")]
@@ -1342,6 +1513,347 @@ fn value_to_command(value: &Value, env: &Uiua) -> UiuaResult<(String, Vec<String
     Ok((command, strings))
 }
 
+/// Decode a JSON string into a value
+///
+/// See [`SysOp::JsonDecode`] for how JSON maps to Uiua values.
+pub(crate) fn json_to_value(json: &str) -> Result<Value, String> {
+    #[cfg(feature = "json")]
+    {
+        let json: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {e}"))?;
+        Ok(json_value_to_value(json))
+    }
+    #[cfg(not(feature = "json"))]
+    {
+        let _ = json;
+        Err("JSON is not supported in this environment".into())
+    }
+}
+
+#[cfg(feature = "json")]
+fn json_value_to_value(json: serde_json::Value) -> Value {
+    use serde_json::Value as Json;
+    match json {
+        Json::Null => f64::NAN.into(),
+        Json::Bool(b) => (b as u8 as f64).into(),
+        Json::Number(n) => n.as_f64().unwrap_or(f64::NAN).into(),
+        Json::String(s) => s.into(),
+        Json::Array(items) => {
+            let values: Vec<Value> = items.into_iter().map(json_value_to_value).collect();
+            let uniform = values.iter().all(|v| matches!(v, Value::Num(_)))
+                && values.windows(2).all(|w| w[0].shape() == w[1].shape());
+            if uniform {
+                if values.is_empty() {
+                    Array::<f64>::default().into()
+                } else {
+                    Value::from_row_values_infallible(values)
+                }
+            } else {
+                Array::from_iter(values.into_iter().map(Boxed)).into()
+            }
+        }
+        Json::Object(map) => {
+            let shape = tiny_vec![map.len(), 2];
+            let mut data = EcoVec::with_capacity(map.len() * 2);
+            for (key, value) in map {
+                data.push(Boxed(key.into()));
+                data.push(Boxed(json_value_to_value(value)));
+            }
+            Array::<Boxed>::new(shape, data).into()
+        }
+    }
+}
+
+/// Encode a value as a JSON string
+///
+/// See [`SysOp::JsonEncode`] for how Uiua values map to JSON.
+pub(crate) fn value_to_json(value: &Value) -> Result<String, String> {
+    #[cfg(feature = "json")]
+    {
+        serde_json::to_string(&value_to_json_value(value)?).map_err(|e| e.to_string())
+    }
+    #[cfg(not(feature = "json"))]
+    {
+        let _ = value;
+        Err("JSON is not supported in this environment".into())
+    }
+}
+
+#[cfg(feature = "json")]
+fn value_to_json_value(value: &Value) -> Result<serde_json::Value, String> {
+    use serde_json::Value as Json;
+    fn num(n: f64) -> Result<Json, String> {
+        if n.fract() == 0.0 && n.abs() < 2f64.powi(53) {
+            Ok(Json::from(n as i64))
+        } else if let Some(n) = serde_json::Number::from_f64(n) {
+            Ok(Json::Number(n))
+        } else {
+            let n = if n.is_nan() {
+                "NaN"
+            } else if n > 0.0 {
+                "∞"
+            } else {
+                "¯∞"
+            };
+            Err(format!("Cannot encode {n} as JSON"))
+        }
+    }
+    /// Nest leaves of `leaf_len` elements according to `shape`
+    fn nested<T>(
+        shape: &[usize],
+        data: &[T],
+        leaf_len: usize,
+        f: &mut dyn FnMut(&[T]) -> Result<Json, String>,
+    ) -> Result<Json, String> {
+        let Some((&len, rest)) = shape.split_first() else {
+            return f(data);
+        };
+        let cell_size = rest.iter().product::<usize>() * leaf_len;
+        (0..len)
+            .map(|i| nested(rest, &data[i * cell_size..(i + 1) * cell_size], leaf_len, f))
+            .collect::<Result<_, _>>()
+            .map(Json::Array)
+    }
+    Ok(match value {
+        Value::Num(arr) => nested(&arr.shape, &arr.data, 1, &mut |n| num(n[0]))?,
+        #[cfg(feature = "bytes")]
+        Value::Byte(arr) => nested(&arr.shape, &arr.data, 1, &mut |b| Ok(Json::from(b[0])))?,
+        Value::Complex(arr) => nested(&arr.shape, &arr.data, 1, &mut |c| {
+            Ok(Json::Array(vec![num(c[0].re)?, num(c[0].im)?]))
+        })?,
+        Value::Char(arr) => {
+            let (outer, row_len) = match arr.shape.split_last() {
+                Some((&row_len, outer)) => (outer, row_len),
+                None => (&[][..], 1),
+            };
+            nested(outer, &arr.data, row_len, &mut |chars| {
+                Ok(Json::String(chars.iter().collect()))
+            })?
+        }
+        Value::Box(arr) => {
+            let is_object = arr.rank() == 2
+                && arr.shape[1] == 2
+                && (arr.data.chunks_exact(2))
+                    .all(|pair| matches!(&pair[0].0, Value::Char(key) if key.rank() <= 1));
+            if is_object {
+                let mut map = serde_json::Map::with_capacity(arr.row_count());
+                for pair in arr.data.chunks_exact(2) {
+                    let Value::Char(key) = &pair[0].0 else {
+                        unreachable!()
+                    };
+                    map.insert(key.data.iter().collect(), value_to_json_value(&pair[1].0)?);
+                }
+                Json::Object(map)
+            } else {
+                nested(&arr.shape, &arr.data, 1, &mut |b| {
+                    value_to_json_value(&b[0].0)
+                })?
+            }
+        }
+    })
+}
+
+fn csv_delimiter(delimiter: &str) -> Result<char, String> {
+    let mut chars = delimiter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !matches!(c, '"' | '\r' | '\n') => Ok(c),
+        _ => Err(format!(
+            "CSV delimiter must be a single character other than a quote or newline, \
+            but it is {delimiter:?}"
+        )),
+    }
+}
+
+/// Decode CSV data into a value
+///
+/// See [`SysOp::CsvDecode`] and [`SysOp::CsvDecodeNums`] for details.
+pub fn csv_to_value(csv: &str, delimiter: char, parse_numbers: bool) -> Result<Value, String> {
+    let mut records: Vec<Vec<String>> = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut quote_line = 1;
+    let mut chars = csv.chars().peekable();
+    loop {
+        match chars.next() {
+            None => {
+                if in_quotes {
+                    return Err(format!(
+                        "Unterminated quoted CSV field starting on line {quote_line}"
+                    ));
+                }
+                if quoted || !field.is_empty() || !record.is_empty() {
+                    record.push(field);
+                    records.push(record);
+                }
+                break;
+            }
+            Some('"') if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            Some(c) if in_quotes => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+            Some('"') if field.is_empty() && !quoted => {
+                in_quotes = true;
+                quoted = true;
+                quote_line = line;
+            }
+            Some(c) if c == delimiter => {
+                record.push(take(&mut field));
+                quoted = false;
+            }
+            Some('\r') if chars.peek() == Some(&'\n') => {}
+            Some('\n') => {
+                if quoted || !field.is_empty() || !record.is_empty() {
+                    record.push(take(&mut field));
+                    records.push(take(&mut record));
+                }
+                quoted = false;
+                line += 1;
+            }
+            Some(c) => field.push(c),
+        }
+    }
+    let width = records.iter().map(Vec::len).max().unwrap_or(0);
+    let mut cells: Vec<Vec<Value>> = (records.into_iter())
+        .map(|mut record| {
+            record.resize(width, String::new());
+            record.into_iter().map(Value::from).collect()
+        })
+        .collect();
+    if parse_numbers {
+        for col in 0..width {
+            // Empty fields are `None`, unparsable fields are `Some(None)`
+            let parsed: Vec<Option<Option<f64>>> = (cells.iter())
+                .map(|row| {
+                    let field: String = match &row[col] {
+                        Value::Char(arr) => arr.data.iter().collect(),
+                        _ => return Some(None),
+                    };
+                    (!field.is_empty()).then(|| parse_number(&field).ok())
+                })
+                .collect();
+            let is_numeric = |fields: &[Option<Option<f64>>]| {
+                fields.iter().all(|f| !matches!(f, Some(None)))
+                    && fields.iter().any(|f| matches!(f, Some(Some(_))))
+            };
+            let start = if is_numeric(&parsed) {
+                0
+            } else if parsed.len() > 1 && is_numeric(&parsed[1..]) {
+                1
+            } else {
+                continue;
+            };
+            for (row, n) in cells.iter_mut().zip(parsed).skip(start) {
+                row[col] = n.flatten().unwrap_or(f64::NAN).into();
+            }
+        }
+    }
+    let shape = tiny_vec![cells.len(), width];
+    let data: EcoVec<Boxed> = cells.into_iter().flatten().map(Boxed).collect();
+    Ok(Array::new(shape, data).into())
+}
+
+/// Encode a value as CSV
+///
+/// See [`SysOp::CsvEncode`] for details.
+pub fn value_to_csv(value: &Value, delimiter: char) -> Result<String, String> {
+    fn cell(value: &Value) -> Result<String, String> {
+        Ok(match value {
+            Value::Char(arr) if arr.rank() <= 1 => arr.data.iter().collect(),
+            Value::Num(arr) if arr.rank() == 0 => arr.data[0].to_string(),
+            #[cfg(feature = "bytes")]
+            Value::Byte(arr) if arr.rank() == 0 => arr.data[0].to_string(),
+            Value::Box(arr) if arr.rank() == 0 => cell(&arr.data[0].0)?,
+            value => {
+                return Err(format!(
+                    "CSV fields must be strings or numbers, but one is {} {}",
+                    FormatShape(value.shape()),
+                    value.type_name()
+                ))
+            }
+        })
+    }
+    fn table<T>(
+        shape: &[usize],
+        data: &[T],
+        leaf_len: usize,
+        f: impl Fn(&[T]) -> Result<String, String>,
+    ) -> Result<Vec<Vec<String>>, String> {
+        let (rows, cols) = match *shape {
+            [] => (1, 1),
+            [cols] => (1, cols),
+            [rows, cols] => (rows, cols),
+            _ => unreachable!(),
+        };
+        (0..rows)
+            .map(|r| {
+                (0..cols)
+                    .map(|c| {
+                        let start = (r * cols + c) * leaf_len;
+                        f(&data[start..start + leaf_len])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    let table_rank = match value {
+        Value::Char(arr) => arr.rank().saturating_sub(1),
+        value => value.rank(),
+    };
+    if table_rank > 2 {
+        return Err(format!(
+            "Cannot encode a rank {} {} array as CSV",
+            value.rank(),
+            value.type_name()
+        ));
+    }
+    let table = match value {
+        Value::Num(arr) => table(&arr.shape, &arr.data, 1, |n| Ok(n[0].to_string()))?,
+        #[cfg(feature = "bytes")]
+        Value::Byte(arr) => table(&arr.shape, &arr.data, 1, |b| Ok(b[0].to_string()))?,
+        Value::Char(arr) => {
+            // Each row of characters is a field, and a list of strings is a column
+            let row_len = arr.shape.last().copied().unwrap_or(1);
+            let shape = match *arr.shape {
+                [rows, _] => vec![rows, 1],
+                ref shape => shape[..shape.len().saturating_sub(1)].to_vec(),
+            };
+            table(&shape, &arr.data, row_len, |s| Ok(s.iter().collect()))?
+        }
+        Value::Box(arr) => table(&arr.shape, &arr.data, 1, |b| cell(&b[0].0))?,
+        Value::Complex(_) => return Err("Cannot encode a complex array as CSV".into()),
+    };
+    let mut csv = String::new();
+    for record in table {
+        for (i, field) in record.into_iter().enumerate() {
+            if i > 0 {
+                csv.push(delimiter);
+            }
+            if field.contains([delimiter, '"', '\r', '\n']) {
+                csv.push('"');
+                csv.push_str(&field.replace('"', "\"\""));
+                csv.push('"');
+            } else {
+                csv.push_str(&field);
+            }
+        }
+        csv.push('\n');
+    }
+    Ok(csv)
+}
+
 #[doc(hidden)]
 pub fn value_to_image_bytes(value: &Value, format: ImageOutputFormat) -> Result<Vec<u8>, String> {
     image_to_bytes(&value_to_image(value)?, format)
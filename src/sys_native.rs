@@ -1,10 +1,11 @@
 use std::{
     any::Any,
+    collections::BTreeMap,
     env,
     fs::{self, File},
-    io::{stderr, stdin, stdout, Read, Write},
+    io::{stderr, stdin, stdout, Cursor, Read, Write},
     net::*,
-    path::Path,
+    path::{Component, Path, PathBuf},
     process::Command,
     slice,
     sync::atomic::{self, AtomicU64},
@@ -16,6 +17,7 @@ use crate::{Handle, SysBackend, UiuaError};
 use bufreaderwriter::seq::BufReaderWriterSeq;
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 
 /// The defualt native system backend
 #[derive(Default)]
@@ -220,6 +222,15 @@ impl SysBackend for NativeSys {
         trash::delete(path).map_err(|e| e.to_string())
     }
     fn read(&self, handle: Handle, len: usize) -> Result<Vec<u8>, String> {
+        if handle == Handle::STDIN {
+            let mut buf = Vec::new();
+            stdin()
+                .lock()
+                .take(len as u64)
+                .read_to_end(&mut buf)
+                .map_err(|e| e.to_string())?;
+            return Ok(buf);
+        }
         Ok(match NATIVE_SYS.get_stream(handle)? {
             SysStream::File(mut file) => {
                 let mut buf = Vec::new();
@@ -240,6 +251,29 @@ impl SysBackend for NativeSys {
             }
         })
     }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        let mut buffer = Vec::new();
+        if handle == Handle::STDIN {
+            for byte in stdin().lock().bytes() {
+                buffer.push(byte.map_err(|e| e.to_string())?);
+                if buffer.ends_with(delim) {
+                    break;
+                }
+            }
+            return Ok(buffer);
+        }
+        loop {
+            let bytes = self.read(handle, 1)?;
+            if bytes.is_empty() {
+                break;
+            }
+            buffer.extend_from_slice(&bytes);
+            if buffer.ends_with(delim) {
+                break;
+            }
+        }
+        Ok(buffer)
+    }
     fn write(&self, handle: Handle, conts: &[u8]) -> Result<(), String> {
         let mut conts = conts;
         let colored;
@@ -632,3 +666,279 @@ fn check_http(mut request: String, hostname: &str) -> Result<String, String> {
 
     Ok(request)
 }
+
+/// A system backend with an in-memory filesystem
+///
+/// Nothing is ever read from or written to the real filesystem.
+/// The host can populate the filesystem before running code and inspect it afterwards
+/// by getting the backend with [`Uiua::downcast_backend`](crate::Uiua::downcast_backend).
+///
+/// Only filesystem operations (including `&i` imports) are supported.
+/// All other system functions fail as they would with a default [`SysBackend`].
+///
+/// ```
+/// use uiua::*;
+///
+/// let sys = MemorySys::new().with_file("input.txt", "Hello");
+/// let mut uiua = Uiua::with_backend(sys);
+/// uiua.load_str(r#"&fwa "output.txt" ⊂:", World!" &fras "input.txt""#).unwrap();
+/// let sys = uiua.downcast_backend::<MemorySys>().unwrap();
+/// assert_eq!(sys.file("output.txt").unwrap(), b"Hello, World!");
+/// ```
+pub struct MemorySys {
+    next_handle: AtomicU64,
+    entries: Mutex<BTreeMap<PathBuf, MemoryEntry>>,
+    streams: DashMap<Handle, MemoryStream>,
+}
+
+enum MemoryEntry {
+    File(Vec<u8>),
+    Dir,
+}
+
+enum MemoryStream {
+    Reader(Cursor<Vec<u8>>),
+    Writer(PathBuf),
+}
+
+impl Default for MemorySys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemorySys {
+    /// Create a new backend with an empty filesystem
+    pub fn new() -> Self {
+        Self {
+            next_handle: Handle::FIRST_UNRESERVED.0.into(),
+            entries: Mutex::new(BTreeMap::new()),
+            streams: DashMap::new(),
+        }
+    }
+    /// Add a file, creating any missing parent directories
+    pub fn with_file(self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> Self {
+        self.add_file(path, contents);
+        self
+    }
+    /// Add a directory, creating any missing parent directories
+    pub fn with_dir(self, path: impl AsRef<Path>) -> Self {
+        self.add_dir(path);
+        self
+    }
+    /// Add a file, creating any missing parent directories
+    ///
+    /// If the file already exists, it is overwritten.
+    pub fn add_file(&self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) {
+        let path = normalize_path(path.as_ref());
+        let mut entries = self.entries.lock();
+        if let Some(parent) = path.parent() {
+            add_dirs(&mut entries, parent);
+        }
+        entries.insert(path, MemoryEntry::File(contents.into()));
+    }
+    /// Add a directory, creating any missing parent directories
+    pub fn add_dir(&self, path: impl AsRef<Path>) {
+        add_dirs(&mut self.entries.lock(), &normalize_path(path.as_ref()));
+    }
+    /// Get the contents of a file
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        match self.entries.lock().get(&normalize_path(path.as_ref()))? {
+            MemoryEntry::File(contents) => Some(contents.clone()),
+            MemoryEntry::Dir => None,
+        }
+    }
+    /// Get the paths of all files
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.entries
+            .lock()
+            .iter()
+            .filter(|(_, entry)| matches!(entry, MemoryEntry::File(_)))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+    fn new_handle(&self) -> Handle {
+        Handle(self.next_handle.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+    fn check_parent(entries: &BTreeMap<PathBuf, MemoryEntry>, path: &Path) -> Result<(), String> {
+        match path.parent() {
+            Some(parent) if parent != Path::new("") => match entries.get(parent) {
+                Some(MemoryEntry::Dir) => Ok(()),
+                Some(MemoryEntry::File(_)) => {
+                    Err(format!("{} is not a directory", parent.display()))
+                }
+                None => Err(format!("Directory not found: {}", parent.display())),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn add_dirs(entries: &mut BTreeMap<PathBuf, MemoryEntry>, path: &Path) {
+    for dir in path.ancestors() {
+        if dir != Path::new("") {
+            entries.insert(dir.into(), MemoryEntry::Dir);
+        }
+    }
+}
+
+impl SysBackend for MemorySys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        let path = normalize_path(path.as_ref());
+        path == Path::new("") || self.entries.lock().contains_key(&path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        let dir = normalize_path(path.as_ref());
+        let entries = self.entries.lock();
+        if dir != Path::new("") && !matches!(entries.get(&dir), Some(MemoryEntry::Dir)) {
+            return Err(format!("Directory not found: {path}"));
+        }
+        Ok(entries
+            .keys()
+            .filter(|p| p.parent() == Some(&dir))
+            .filter_map(|p| p.file_name())
+            .map(|name| Path::new(path).join(name).to_string_lossy().into())
+            .collect())
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        let normalized = normalize_path(path.as_ref());
+        if normalized == Path::new("") {
+            return Ok(false);
+        }
+        match self.entries.lock().get(&normalized) {
+            Some(entry) => Ok(matches!(entry, MemoryEntry::File(_))),
+            None => Err(format!("File not found: {path}")),
+        }
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        let normalized = normalize_path(path.as_ref());
+        let mut entries = self.entries.lock();
+        if entries.remove(&normalized).is_none() {
+            return Err(format!("File not found: {path}"));
+        }
+        entries.retain(|p, _| !p.starts_with(&normalized));
+        Ok(())
+    }
+    fn open_file(&self, path: &Path) -> Result<Handle, String> {
+        let contents = match self.entries.lock().get(&normalize_path(path)) {
+            Some(MemoryEntry::File(contents)) => contents.clone(),
+            Some(MemoryEntry::Dir) => return Err(format!("{} is a directory", path.display())),
+            None => return Err(format!("File not found: {}", path.display())),
+        };
+        let handle = self.new_handle();
+        self.streams
+            .insert(handle, MemoryStream::Reader(Cursor::new(contents)));
+        Ok(handle)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        let normalized = normalize_path(path);
+        let mut entries = self.entries.lock();
+        Self::check_parent(&entries, &normalized)?;
+        if let Some(MemoryEntry::Dir) = entries.get(&normalized) {
+            return Err(format!("{} is a directory", path.display()));
+        }
+        entries.insert(normalized.clone(), MemoryEntry::File(Vec::new()));
+        drop(entries);
+        let handle = self.new_handle();
+        self.streams
+            .insert(handle, MemoryStream::Writer(normalized));
+        Ok(handle)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        let mut stream = self.streams.get_mut(&handle).ok_or("Invalid file handle")?;
+        match &mut *stream {
+            MemoryStream::Reader(cursor) => {
+                let mut buf = Vec::new();
+                cursor
+                    .take(count as u64)
+                    .read_to_end(&mut buf)
+                    .map_err(|e| e.to_string())?;
+                Ok(buf)
+            }
+            MemoryStream::Writer(_) => Err("Cannot read from a file opened for writing".into()),
+        }
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        let mut stream = self.streams.get_mut(&handle).ok_or("Invalid file handle")?;
+        match &mut *stream {
+            MemoryStream::Reader(cursor) => {
+                let start = (cursor.position() as usize).min(cursor.get_ref().len());
+                let rest = &cursor.get_ref()[start..];
+                let len = if delim.is_empty() {
+                    rest.len()
+                } else {
+                    rest.windows(delim.len())
+                        .position(|window| window == delim)
+                        .map_or(rest.len(), |i| i + delim.len())
+                };
+                let buf = rest[..len].to_vec();
+                cursor.set_position((start + len) as u64);
+                Ok(buf)
+            }
+            MemoryStream::Writer(_) => Err("Cannot read from a file opened for writing".into()),
+        }
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        let stream = self.streams.get(&handle).ok_or("Invalid file handle")?;
+        match &*stream {
+            MemoryStream::Reader(_) => Err("Cannot write to a file opened for reading".into()),
+            MemoryStream::Writer(path) => {
+                let mut entries = self.entries.lock();
+                match entries
+                    .entry(path.clone())
+                    .or_insert_with(|| MemoryEntry::File(Vec::new()))
+                {
+                    MemoryEntry::File(file) => {
+                        file.extend_from_slice(contents);
+                        Ok(())
+                    }
+                    MemoryEntry::Dir => Err(format!("{} is a directory", path.display())),
+                }
+            }
+        }
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        match self.entries.lock().get(&normalize_path(path)) {
+            Some(MemoryEntry::File(contents)) => Ok(contents.clone()),
+            Some(MemoryEntry::Dir) => Err(format!("{} is a directory", path.display())),
+            None => Err(format!("File not found: {}", path.display())),
+        }
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        let normalized = normalize_path(path);
+        let mut entries = self.entries.lock();
+        Self::check_parent(&entries, &normalized)?;
+        if let Some(MemoryEntry::Dir) = entries.get(&normalized) {
+            return Err(format!("{} is a directory", path.display()));
+        }
+        entries.insert(normalized, MemoryEntry::File(contents.to_vec()));
+        Ok(())
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        if self.streams.remove(&handle).is_some() {
+            Ok(())
+        } else {
+            Err("Invalid stream handle".to_string())
+        }
+    }
+}
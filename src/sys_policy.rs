@@ -0,0 +1,473 @@
+use std::{
+    any::Any,
+    env,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use image::DynamicImage;
+
+use crate::{sys::current_sys_op, AudioStreamFn, Handle, SysBackend, SysOp, UiuaError};
+
+/// A set of permissions for a [`PolicySys`]
+///
+/// The default policy denies everything.
+///
+/// ```
+/// use uiua::*;
+///
+/// let policy = SysPolicy::default()
+///     .allow_read("data")
+///     .allow_net("example.com:443");
+/// let mut uiua = Uiua::with_backend(PolicySys::new(NativeSys, policy));
+/// let err = uiua.load_str(r#"&var "HOME""#).unwrap_err();
+/// assert!(err.to_string().contains("&var"));
+/// let err = uiua.load_str(r#"&fras "secret.txt""#).unwrap_err();
+/// assert!(err.to_string().contains("&fras"));
+/// let err = uiua.backend().file_read_all("secret.txt".as_ref()).unwrap_err();
+/// assert!(err.contains("&frab"));
+///
+/// // The backend's methods are restricted too
+/// assert_eq!(uiua.backend().var("HOME"), None);
+/// assert!(uiua.backend().run_command_capture("ls", &[]).is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SysPolicy {
+    read: Permission<PathBuf>,
+    write: Permission<PathBuf>,
+    net: Permission<String>,
+    run: bool,
+    invoke: bool,
+    change_directory: bool,
+    env: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Permission<T> {
+    All,
+    Only(Vec<T>),
+}
+
+impl<T> Default for Permission<T> {
+    fn default() -> Self {
+        Permission::Only(Vec::new())
+    }
+}
+
+impl<T> Permission<T> {
+    fn add(&mut self, item: T) {
+        match self {
+            Permission::All => {}
+            Permission::Only(items) => items.push(item),
+        }
+    }
+    fn is_denied(&self) -> bool {
+        matches!(self, Permission::Only(items) if items.is_empty())
+    }
+}
+
+impl SysPolicy {
+    /// A policy that allows everything
+    pub fn allow_all() -> Self {
+        Self {
+            read: Permission::All,
+            write: Permission::All,
+            net: Permission::All,
+            run: true,
+            invoke: true,
+            change_directory: true,
+            env: true,
+        }
+    }
+    /// Allow reading files in a directory or a single file
+    pub fn allow_read(mut self, path: impl Into<PathBuf>) -> Self {
+        self.read.add(path.into());
+        self
+    }
+    /// Allow reading any file
+    pub fn allow_read_all(mut self) -> Self {
+        self.read = Permission::All;
+        self
+    }
+    /// Deny reading any file
+    pub fn deny_read(mut self) -> Self {
+        self.read = Permission::default();
+        self
+    }
+    /// Allow writing and deleting files in a directory or a single file
+    pub fn allow_write(mut self, path: impl Into<PathBuf>) -> Self {
+        self.write.add(path.into());
+        self
+    }
+    /// Allow writing and deleting any file
+    pub fn allow_write_all(mut self) -> Self {
+        self.write = Permission::All;
+        self
+    }
+    /// Deny writing and deleting any file
+    pub fn deny_write(mut self) -> Self {
+        self.write = Permission::default();
+        self
+    }
+    /// Allow TCP connections to and listeners on a host
+    ///
+    /// The host may be given as `host` to allow any port or as `host:port` to allow a single port.
+    pub fn allow_net(mut self, host: impl Into<String>) -> Self {
+        self.net.add(host.into());
+        self
+    }
+    /// Allow any TCP connections and listeners
+    pub fn allow_net_all(mut self) -> Self {
+        self.net = Permission::All;
+        self
+    }
+    /// Deny all TCP connections and listeners
+    pub fn deny_net(mut self) -> Self {
+        self.net = Permission::default();
+        self
+    }
+    /// Set whether running commands with `&runi` and `&runc` is allowed
+    pub fn with_run(mut self, allow: bool) -> Self {
+        self.run = allow;
+        self
+    }
+    /// Set whether invoking paths with `&invk` is allowed
+    pub fn with_invoke(mut self, allow: bool) -> Self {
+        self.invoke = allow;
+        self
+    }
+    /// Set whether changing the current directory with `&cd` is allowed
+    pub fn with_change_directory(mut self, allow: bool) -> Self {
+        self.change_directory = allow;
+        self
+    }
+    /// Set whether reading environment variables with `&var` is allowed
+    pub fn with_env(mut self, allow: bool) -> Self {
+        self.env = allow;
+        self
+    }
+    /// Check whether a system function is allowed at all
+    pub fn check_sys_op(&self, op: SysOp) -> Result<(), String> {
+        let allowed = match op {
+            SysOp::RunInherit | SysOp::RunCapture => self.run,
+            SysOp::Invoke => self.invoke,
+            SysOp::ChangeDirectory => self.change_directory,
+            SysOp::Var => self.env,
+            SysOp::TcpListen | SysOp::TcpConnect => !self.net.is_denied(),
+            SysOp::FOpen
+            | SysOp::FExists
+            | SysOp::FListDir
+            | SysOp::FIsFile
+            | SysOp::FReadAllStr
+            | SysOp::FReadAllBytes
+            | SysOp::Import => !self.read.is_denied(),
+            SysOp::FCreate | SysOp::FDelete | SysOp::FTrash | SysOp::FWriteAll => {
+                !self.write.is_denied()
+            }
+            _ => true,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(denied(op, ""))
+        }
+    }
+    /// Check whether a system function may read a path
+    pub fn check_read(&self, op: SysOp, path: &Path) -> Result<(), String> {
+        if path_allowed(&self.read, path) {
+            Ok(())
+        } else {
+            Err(denied(op, &format!(" to read {}", path.display())))
+        }
+    }
+    /// Check whether a system function may write to or delete a path
+    pub fn check_write(&self, op: SysOp, path: &Path) -> Result<(), String> {
+        if path_allowed(&self.write, path) {
+            Ok(())
+        } else {
+            Err(denied(op, &format!(" to write to {}", path.display())))
+        }
+    }
+    /// Check whether a system function may connect to or listen on a TCP address
+    pub fn check_net(&self, op: SysOp, addr: &str) -> Result<(), String> {
+        let allowed = match &self.net {
+            Permission::All => true,
+            Permission::Only(hosts) => {
+                let (host, port) = addr.rsplit_once(':').unwrap_or((addr, ""));
+                hosts.iter().any(|allowed| match allowed.rsplit_once(':') {
+                    Some((allowed_host, allowed_port)) => {
+                        allowed_host == host && allowed_port == port
+                    }
+                    None => allowed == host,
+                })
+            }
+        };
+        if allowed {
+            Ok(())
+        } else if op == SysOp::TcpListen {
+            Err(denied(op, &format!(" to listen on {addr}")))
+        } else {
+            Err(denied(op, &format!(" to connect to {addr}")))
+        }
+    }
+}
+
+fn denied(op: SysOp, action: &str) -> String {
+    format!(
+        "{} ({}) is not allowed{action} by the system policy",
+        op.name(),
+        op.long_name()
+    )
+}
+
+fn path_allowed(permission: &Permission<PathBuf>, path: &Path) -> bool {
+    match permission {
+        Permission::All => true,
+        Permission::Only(roots) => {
+            let path = resolve_path(path);
+            roots
+                .iter()
+                .any(|root| path.starts_with(resolve_path(root)))
+        }
+    }
+}
+
+/// Resolve a path to an absolute path, following symlinks where possible
+fn resolve_path(path: &Path) -> PathBuf {
+    let absolute = env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.into());
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    // Canonicalize the longest existing ancestor so that
+    // symlinks cannot be used to escape an allowed directory
+    let mut existing = normalized.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canon) = existing.canonicalize() {
+            return rest
+                .into_iter()
+                .rev()
+                .fold(canon, |path, name| path.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return normalized,
+        }
+    }
+}
+
+/// Get the system function being run, for backend methods that several of them use
+///
+/// The default is used when a method is called directly rather than by a system function.
+fn current_op(default: SysOp) -> SysOp {
+    current_sys_op().unwrap_or(default)
+}
+
+/// A system backend that restricts another backend with a [`SysPolicy`]
+///
+/// Anything not allowed by the policy fails with an error.
+/// Everything else is passed on to the wrapped backend.
+pub struct PolicySys<B> {
+    inner: B,
+    policy: SysPolicy,
+}
+
+impl<B: SysBackend> PolicySys<B> {
+    /// Wrap a backend with a policy
+    pub fn new(inner: B, policy: SysPolicy) -> Self {
+        Self { inner, policy }
+    }
+    /// Get the policy
+    pub fn policy(&self) -> &SysPolicy {
+        &self.policy
+    }
+    /// Get the wrapped backend
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+    /// Get the wrapped backend mutably
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+    /// Unwrap the backend
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: SysBackend> SysBackend for PolicySys<B> {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn check_sys_op(&self, op: SysOp) -> Result<(), String> {
+        self.policy.check_sys_op(op)?;
+        self.inner.check_sys_op(op)
+    }
+    fn save_error_color(&self, error: &UiuaError) {
+        self.inner.save_error_color(error)
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stdout(s)
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stderr(s)
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.inner.print_str_trace(s)
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        self.inner.scan_line_stdin()
+    }
+    fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String> {
+        self.inner.set_raw_mode(raw_mode)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.policy.check_sys_op(SysOp::Var).ok()?;
+        self.inner.var(name)
+    }
+    fn term_size(&self) -> Result<(usize, usize), String> {
+        self.inner.term_size()
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        (self.policy.check_read(SysOp::FExists, path.as_ref())).is_ok()
+            && self.inner.file_exists(path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        self.policy.check_read(SysOp::FListDir, path.as_ref())?;
+        self.inner.list_dir(path)
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        self.policy.check_read(SysOp::FIsFile, path.as_ref())?;
+        self.inner.is_file(path)
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        self.policy.check_write(SysOp::FDelete, path.as_ref())?;
+        self.inner.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.policy.check_write(SysOp::FTrash, path.as_ref())?;
+        self.inner.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.inner.read(handle, count)
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.inner.read_until(handle, delim)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        self.inner.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        self.policy.check_write(SysOp::FCreate, path)?;
+        self.inner.create_file(path)
+    }
+    fn open_file(&self, path: &Path) -> Result<Handle, String> {
+        self.policy.check_read(SysOp::FOpen, path)?;
+        self.inner.open_file(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        self.policy
+            .check_read(current_op(SysOp::FReadAllBytes), path)?;
+        self.inner.file_read_all(path)
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.policy.check_write(SysOp::FWriteAll, path)?;
+        self.inner.file_write_all(path, contents)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        self.inner.sleep(seconds)
+    }
+    fn show_image(&self, image: DynamicImage) -> Result<(), String> {
+        self.inner.show_image(image)
+    }
+    fn show_gif(&self, gif_bytes: Vec<u8>) -> Result<(), String> {
+        self.inner.show_gif(gif_bytes)
+    }
+    fn play_audio(&self, wave_bytes: Vec<u8>) -> Result<(), String> {
+        self.inner.play_audio(wave_bytes)
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        self.inner.audio_sample_rate()
+    }
+    fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+        self.inner.stream_audio(f)
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        self.policy.check_net(SysOp::TcpListen, addr)?;
+        self.inner.tcp_listen(addr)
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        self.inner.tcp_accept(handle)
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.policy.check_net(SysOp::TcpConnect, addr)?;
+        self.inner.tcp_connect(addr)
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<String, String> {
+        self.inner.tcp_addr(handle)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        self.inner.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.inner.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.inner.tcp_set_write_timeout(handle, timeout)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        self.inner.close(handle)
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        self.policy.check_sys_op(SysOp::Invoke)?;
+        self.inner.invoke(path)
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        self.policy.check_sys_op(SysOp::RunInherit)?;
+        self.inner.run_command_inherit(command, args)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        self.policy.check_sys_op(SysOp::RunCapture)?;
+        self.inner.run_command_capture(command, args)
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        self.policy.check_sys_op(SysOp::ChangeDirectory)?;
+        self.inner.change_directory(path)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        self.inner.https_get(request, handle)
+    }
+    fn now(&self) -> f64 {
+        self.inner.now()
+    }
+    fn rand(&self) -> f64 {
+        self.inner.rand()
+    }
+}
@@ -0,0 +1,535 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    io::{stderr, stdout, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use image::DynamicImage;
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{AudioStreamFn, Handle, SysBackend, SysOp, UiuaError};
+
+/// A nondeterministic call made to a [`SysBackend`]
+///
+/// Only calls whose results can differ between runs are recorded.
+/// Calls that only have side effects, like writing to a file, are not.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum SysCall {
+    ScanLineStdin,
+    Read { handle: Handle, count: usize },
+    ReadUntil { handle: Handle, delim: Vec<u8> },
+    Var { name: String },
+    TermSize,
+    FileExists { path: String },
+    ListDir { path: String },
+    IsFile { path: String },
+    CreateFile { path: PathBuf },
+    OpenFile { path: PathBuf },
+    FileReadAll { path: PathBuf },
+    TcpListen { addr: String },
+    TcpAccept { handle: Handle },
+    TcpConnect { addr: String },
+    TcpAddr { handle: Handle },
+    RunCommandInherit { command: String, args: Vec<String> },
+    RunCommandCapture { command: String, args: Vec<String> },
+    HttpsGet { request: String, handle: Handle },
+    Now,
+    Rand,
+}
+
+/// A recorded [`SysCall`] and its result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SysTranscriptEntry {
+    /// The call that was made
+    pub call: SysCall,
+    /// The result of the call
+    pub result: serde_json::Value,
+}
+
+/// A sequence of [`SysCall`]s recorded by a [`RecordSys`]
+///
+/// It can be replayed with a [`ReplaySys`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SysTranscript {
+    /// The recorded calls, in order
+    pub calls: Vec<SysTranscriptEntry>,
+}
+
+impl SysTranscript {
+    /// Parse a transcript from JSON
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid transcript: {e}"))
+    }
+    /// Serialize the transcript to JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// A backend that records the nondeterministic calls made to another backend
+///
+/// The resulting [`SysTranscript`] can be replayed with a [`ReplaySys`].
+///
+/// ```
+/// use uiua::*;
+///
+/// let mut uiua = Uiua::with_backend(RecordSys::new(NativeSys));
+/// uiua.load_str("⚂").unwrap();
+/// let recorded = uiua.pop_num().unwrap();
+/// let transcript = uiua.downcast_backend::<RecordSys<NativeSys>>().unwrap().transcript();
+///
+/// let mut uiua = Uiua::with_backend(ReplaySys::new(transcript));
+/// uiua.load_str("⚂").unwrap();
+/// assert_eq!(uiua.pop_num().unwrap(), recorded);
+/// ```
+pub struct RecordSys<B> {
+    inner: B,
+    calls: Mutex<Vec<SysTranscriptEntry>>,
+}
+
+impl<B: SysBackend> RecordSys<B> {
+    /// Create a new recording backend wrapping `inner`
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+    /// Get the wrapped backend
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+    /// Get the calls recorded so far
+    pub fn transcript(&self) -> SysTranscript {
+        SysTranscript {
+            calls: self.calls.lock().clone(),
+        }
+    }
+    fn record<T: Serialize>(&self, call: SysCall, result: T) -> T {
+        let value = serde_json::to_value(&result).unwrap_or(serde_json::Value::Null);
+        self.calls.lock().push(SysTranscriptEntry {
+            call,
+            result: value,
+        });
+        result
+    }
+}
+
+impl<B: SysBackend> SysBackend for RecordSys<B> {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn check_sys_op(&self, op: SysOp) -> Result<(), String> {
+        self.inner.check_sys_op(op)
+    }
+    fn save_error_color(&self, error: &UiuaError) {
+        self.inner.save_error_color(error)
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stdout(s)
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.inner.print_str_stderr(s)
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.inner.print_str_trace(s)
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        self.record(SysCall::ScanLineStdin, self.inner.scan_line_stdin())
+    }
+    fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String> {
+        self.inner.set_raw_mode(raw_mode)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        let call = SysCall::Var { name: name.into() };
+        self.record(call, self.inner.var(name))
+    }
+    fn term_size(&self) -> Result<(usize, usize), String> {
+        self.record(SysCall::TermSize, self.inner.term_size())
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        let call = SysCall::FileExists { path: path.into() };
+        self.record(call, self.inner.file_exists(path))
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        let call = SysCall::ListDir { path: path.into() };
+        self.record(call, self.inner.list_dir(path))
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        let call = SysCall::IsFile { path: path.into() };
+        self.record(call, self.inner.is_file(path))
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        self.inner.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.inner.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        let call = SysCall::Read { handle, count };
+        self.record(call, self.inner.read(handle, count))
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        let call = SysCall::ReadUntil {
+            handle,
+            delim: delim.into(),
+        };
+        self.record(call, self.inner.read_until(handle, delim))
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        self.inner.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        let call = SysCall::CreateFile { path: path.into() };
+        self.record(call, self.inner.create_file(path))
+    }
+    fn open_file(&self, path: &Path) -> Result<Handle, String> {
+        let call = SysCall::OpenFile { path: path.into() };
+        self.record(call, self.inner.open_file(path))
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        let call = SysCall::FileReadAll { path: path.into() };
+        self.record(call, self.inner.file_read_all(path))
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.inner.file_write_all(path, contents)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        self.inner.sleep(seconds)
+    }
+    fn show_image(&self, image: DynamicImage) -> Result<(), String> {
+        self.inner.show_image(image)
+    }
+    fn show_gif(&self, gif_bytes: Vec<u8>) -> Result<(), String> {
+        self.inner.show_gif(gif_bytes)
+    }
+    fn play_audio(&self, wave_bytes: Vec<u8>) -> Result<(), String> {
+        self.inner.play_audio(wave_bytes)
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        self.inner.audio_sample_rate()
+    }
+    fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+        self.inner.stream_audio(f)
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        let call = SysCall::TcpListen { addr: addr.into() };
+        self.record(call, self.inner.tcp_listen(addr))
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        let call = SysCall::TcpAccept { handle };
+        self.record(call, self.inner.tcp_accept(handle))
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        let call = SysCall::TcpConnect { addr: addr.into() };
+        self.record(call, self.inner.tcp_connect(addr))
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<String, String> {
+        let call = SysCall::TcpAddr { handle };
+        self.record(call, self.inner.tcp_addr(handle))
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        self.inner.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.inner.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.inner.tcp_set_write_timeout(handle, timeout)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        self.inner.close(handle)
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        self.inner.invoke(path)
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        let call = SysCall::RunCommandInherit {
+            command: command.into(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+        };
+        self.record(call, self.inner.run_command_inherit(command, args))
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        let call = SysCall::RunCommandCapture {
+            command: command.into(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+        };
+        self.record(call, self.inner.run_command_capture(command, args))
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        self.inner.change_directory(path)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        let call = SysCall::HttpsGet {
+            request: request.into(),
+            handle,
+        };
+        self.record(call, self.inner.https_get(request, handle))
+    }
+    fn now(&self) -> f64 {
+        self.record(SysCall::Now, self.inner.now())
+    }
+    fn rand(&self) -> f64 {
+        self.record(SysCall::Rand, self.inner.rand())
+    }
+}
+
+/// A backend that replays a [`SysTranscript`] recorded by a [`RecordSys`]
+///
+/// Recorded calls are answered from the transcript without touching the real system.
+/// Calls that only have side effects succeed without doing anything.
+/// Output is captured and can be retrieved with [`ReplaySys::stdout`] and [`ReplaySys::stderr`].
+///
+/// If the program makes a call that does not match the next recorded call,
+/// the replay has diverged. That call and all later recorded calls fail,
+/// and the reason can be retrieved with [`ReplaySys::divergence`].
+/// Calls that cannot fail, like [`SysBackend::rand`], return a default value instead,
+/// and the next system function fails.
+pub struct ReplaySys {
+    calls: Mutex<VecDeque<SysTranscriptEntry>>,
+    total: usize,
+    divergence: Mutex<Option<String>>,
+    stdout: Mutex<String>,
+    stderr: Mutex<String>,
+    echo: bool,
+}
+
+impl ReplaySys {
+    /// Create a new replaying backend
+    pub fn new(transcript: SysTranscript) -> Self {
+        Self {
+            total: transcript.calls.len(),
+            calls: Mutex::new(transcript.calls.into()),
+            divergence: Mutex::new(None),
+            stdout: Mutex::new(String::new()),
+            stderr: Mutex::new(String::new()),
+            echo: false,
+        }
+    }
+    /// Set whether output should also be printed to the real stdout and stderr
+    pub fn with_echo(self, echo: bool) -> Self {
+        Self { echo, ..self }
+    }
+    /// Get everything printed to stdout
+    pub fn stdout(&self) -> String {
+        self.stdout.lock().clone()
+    }
+    /// Get everything printed to stderr
+    pub fn stderr(&self) -> String {
+        self.stderr.lock().clone()
+    }
+    /// Get the reason the replay diverged from the transcript, if it did
+    pub fn divergence(&self) -> Option<String> {
+        self.divergence.lock().clone()
+    }
+    /// Get the number of recorded calls that have not been replayed
+    pub fn remaining(&self) -> usize {
+        self.calls.lock().len()
+    }
+    fn replay<T: DeserializeOwned>(&self, call: SysCall) -> Result<T, String> {
+        let mut divergence = self.divergence.lock();
+        if let Some(message) = &*divergence {
+            return Err(message.clone());
+        }
+        let mut calls = self.calls.lock();
+        let number = self.total - calls.len() + 1;
+        let message = match calls.pop_front() {
+            Some(entry) if entry.call == call => match serde_json::from_value(entry.result) {
+                Ok(result) => return Ok(result),
+                Err(e) => format!("Replay failed at call {number}: invalid recorded result: {e}"),
+            },
+            Some(entry) => format!(
+                "Replay diverged at call {number}: expected {:?} but the program made {call:?}",
+                entry.call
+            ),
+            None => format!(
+                "Replay diverged at call {number}: the transcript has ended \
+                but the program made {call:?}"
+            ),
+        };
+        *divergence = Some(message.clone());
+        Err(message)
+    }
+    fn replay_result<T: DeserializeOwned>(&self, call: SysCall) -> Result<T, String> {
+        self.replay::<Result<T, String>>(call)?
+    }
+}
+
+impl SysBackend for ReplaySys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn check_sys_op(&self, _: SysOp) -> Result<(), String> {
+        match self.divergence() {
+            Some(message) => Err(message),
+            None => Ok(()),
+        }
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        if self.echo {
+            let mut stdout = stdout().lock();
+            stdout.write_all(s.as_bytes()).map_err(|e| e.to_string())?;
+            stdout.flush().map_err(|e| e.to_string())?;
+        }
+        self.stdout.lock().push_str(s);
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        if self.echo {
+            stderr()
+                .write_all(s.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+        self.stderr.lock().push_str(s);
+        Ok(())
+    }
+    fn print_str_trace(&self, s: &str) {
+        _ = self.print_str_stderr(s);
+    }
+    fn scan_line_stdin(&self) -> Result<Option<String>, String> {
+        self.replay_result(SysCall::ScanLineStdin)
+    }
+    fn set_raw_mode(&self, _: bool) -> Result<(), String> {
+        Ok(())
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.replay(SysCall::Var { name: name.into() })
+            .unwrap_or_default()
+    }
+    fn term_size(&self) -> Result<(usize, usize), String> {
+        self.replay_result(SysCall::TermSize)
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        (self.replay(SysCall::FileExists { path: path.into() })).unwrap_or_default()
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        self.replay_result(SysCall::ListDir { path: path.into() })
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        self.replay_result(SysCall::IsFile { path: path.into() })
+    }
+    fn delete(&self, _: &str) -> Result<(), String> {
+        Ok(())
+    }
+    fn trash(&self, _: &str) -> Result<(), String> {
+        Ok(())
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.replay_result(SysCall::Read { handle, count })
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.replay_result(SysCall::ReadUntil {
+            handle,
+            delim: delim.into(),
+        })
+    }
+    fn write(&self, _: Handle, _: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        self.replay_result(SysCall::CreateFile { path: path.into() })
+    }
+    fn open_file(&self, path: &Path) -> Result<Handle, String> {
+        self.replay_result(SysCall::OpenFile { path: path.into() })
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        self.replay_result(SysCall::FileReadAll { path: path.into() })
+    }
+    fn file_write_all(&self, _: &Path, _: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+    fn sleep(&self, _: f64) -> Result<(), String> {
+        Ok(())
+    }
+    fn show_image(&self, _: DynamicImage) -> Result<(), String> {
+        Ok(())
+    }
+    fn show_gif(&self, _: Vec<u8>) -> Result<(), String> {
+        Ok(())
+    }
+    fn play_audio(&self, _: Vec<u8>) -> Result<(), String> {
+        Ok(())
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        self.replay_result(SysCall::TcpListen { addr: addr.into() })
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        self.replay_result(SysCall::TcpAccept { handle })
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.replay_result(SysCall::TcpConnect { addr: addr.into() })
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<String, String> {
+        self.replay_result(SysCall::TcpAddr { handle })
+    }
+    fn tcp_set_non_blocking(&self, _: Handle, _: bool) -> Result<(), String> {
+        Ok(())
+    }
+    fn tcp_set_read_timeout(&self, _: Handle, _: Option<Duration>) -> Result<(), String> {
+        Ok(())
+    }
+    fn tcp_set_write_timeout(&self, _: Handle, _: Option<Duration>) -> Result<(), String> {
+        Ok(())
+    }
+    fn close(&self, _: Handle) -> Result<(), String> {
+        Ok(())
+    }
+    fn invoke(&self, _: &str) -> Result<(), String> {
+        Ok(())
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        self.replay_result(SysCall::RunCommandInherit {
+            command: command.into(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+        })
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        self.replay_result(SysCall::RunCommandCapture {
+            command: command.into(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+        })
+    }
+    fn change_directory(&self, _: &str) -> Result<(), String> {
+        Ok(())
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        self.replay_result(SysCall::HttpsGet {
+            request: request.into(),
+            handle,
+        })
+    }
+    fn now(&self) -> f64 {
+        self.replay(SysCall::Now).unwrap_or_default()
+    }
+    fn rand(&self) -> f64 {
+        self.replay(SysCall::Rand).unwrap_or_default()
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    fs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+};
+
+use crate::{
+    ast::{Item, Modifier, Word},
+    lex::{CodeSpan, Sp, Span},
+    parse::parse,
+    primitive::Primitive,
+    run::RunMode,
+    value::Value,
+    Report, ReportKind, Uiua, UiuaError, UiuaResult,
+};
+
+/// Options for [`Uiua::run_tests`]
+#[derive(Debug, Clone, Default)]
+pub struct TestOptions {
+    /// Only run tests whose name contains this string, or that contain this line number
+    pub filter: Option<String>,
+    /// Treat each top-level line in a test scope as its own test case
+    pub per_line: bool,
+}
+
+/// The result of running a single test case
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    /// The name of the test
+    ///
+    /// This is the first comment line in the test scope, or its location if there is none.
+    /// Per-line cases are also named by the first line of their code.
+    pub name: String,
+    /// The span of the test scope or line
+    pub span: CodeSpan,
+    /// The time taken to run the test in milliseconds
+    pub duration: f64,
+    /// Why the test failed, if it did
+    pub failure: Option<TestFailure>,
+}
+
+impl TestCase {
+    /// Check if the test passed
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Why a test case failed
+#[derive(Debug, Clone)]
+pub enum TestFailure {
+    /// The test returned an error
+    Error(UiuaError),
+    /// A `⍤⊃⋅∘≍` assertion got a value other than the expected one
+    Mismatch {
+        /// The expected value
+        expected: Value,
+        /// The actual value
+        actual: Value,
+        /// The span of the assertion's line
+        span: CodeSpan,
+    },
+}
+
+impl TestFailure {
+    /// Get a report for the failure
+    pub fn report(&self) -> Report {
+        match self {
+            TestFailure::Error(error) => error.report(),
+            TestFailure::Mismatch {
+                expected,
+                actual,
+                span,
+            } => Report::new_multi(
+                ReportKind::Error,
+                [(
+                    format!(
+                        "Assertion failed: expected {}, but got {}",
+                        expected.show(),
+                        actual.show()
+                    ),
+                    Span::Code(span.clone()),
+                )],
+            ),
+        }
+    }
+}
+
+impl Uiua {
+    /// Run the test scopes of a Uiua file, continuing after failures
+    ///
+    /// See [`Uiua::run_tests`]
+    pub fn run_test_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &TestOptions,
+    ) -> UiuaResult<Vec<TestCase>> {
+        let path = path.as_ref();
+        let input = fs::read_to_string(path).map_err(|e| UiuaError::Load(path.into(), e.into()))?;
+        self.run_tests(&input, Some(path), options)
+    }
+    /// Run the test scopes of some code, continuing after failures
+    ///
+    /// Each `---` test scope is a test case, or each of its top-level lines if
+    /// [`TestOptions::per_line`] is set. Code outside of test scopes is run as in [`RunMode::Test`].
+    ///
+    /// Errors outside of test scopes stop the run and are returned.
+    ///
+    /// ```
+    /// use uiua::*;
+    ///
+    /// let code = "\
+    /// ---
+    /// ## Addition
+    /// ⍤⊃⋅∘≍ 3 +1 2
+    /// ---
+    /// ---
+    /// ## Subtraction
+    /// ⍤⊃⋅∘≍ 3 -1 5
+    /// ---";
+    /// let cases = Uiua::with_native_sys()
+    ///     .run_tests(code, None, &TestOptions::default())
+    ///     .unwrap();
+    /// assert_eq!(cases.len(), 2);
+    /// assert!(cases[0].passed());
+    /// assert_eq!(cases[1].name, "Subtraction");
+    /// assert!(matches!(cases[1].failure, Some(TestFailure::Mismatch { .. })));
+    /// ```
+    pub fn run_tests(
+        &mut self,
+        input: &str,
+        path: Option<&Path>,
+        options: &TestOptions,
+    ) -> UiuaResult<Vec<TestCase>> {
+        self.mode = RunMode::Test;
+        let (items, errors, diagnostics) = parse(input, path);
+        if self.print_diagnostics {
+            for diagnostic in diagnostics {
+                println!("{}", diagnostic.report());
+            }
+        } else {
+            self.diagnostics.extend(diagnostics);
+        }
+        if !errors.is_empty() {
+            return Err(errors.into());
+        }
+        if let Some(path) = path {
+            self.current_imports.lock().push(path.into());
+        }
+        let mut cases = Vec::new();
+        let mut res = Ok(());
+        for item in items {
+            match item {
+                Item::TestScope(scope) => {
+                    let name = scope_name(&scope);
+                    if let Some(filter) = &options.filter {
+                        if !name.contains(filter.as_str()) && !covers_line(&scope.span, filter) {
+                            continue;
+                        }
+                    }
+                    if options.per_line {
+                        self.in_scope(|env| {
+                            for item in scope.value {
+                                let Item::Words(words) = &item else {
+                                    let res = env.catch_crash(|env| env.items(vec![item], true));
+                                    if let Err(error) = res {
+                                        cases.push(TestCase {
+                                            name: name.clone(),
+                                            span: scope.span.clone(),
+                                            duration: 0.0,
+                                            failure: Some(TestFailure::Error(error)),
+                                        });
+                                    }
+                                    continue;
+                                };
+                                let Some(span) = words_span(words) else {
+                                    continue;
+                                };
+                                let start = instant::now();
+                                let failure = env.test_item(item);
+                                if let Some(filter) = &options.filter {
+                                    if !name.contains(filter.as_str())
+                                        && !covers_line(&span, filter)
+                                    {
+                                        continue;
+                                    }
+                                }
+                                cases.push(TestCase {
+                                    name: format!("{name}: {}", first_line(&span)),
+                                    span,
+                                    duration: instant::now() - start,
+                                    failure,
+                                });
+                            }
+                            Ok(())
+                        })?;
+                    } else {
+                        let start = instant::now();
+                        let mut failure = None;
+                        self.in_scope(|env| {
+                            for item in scope.value {
+                                failure = env.test_item(item);
+                                if failure.is_some() {
+                                    break;
+                                }
+                            }
+                            Ok(())
+                        })?;
+                        cases.push(TestCase {
+                            name,
+                            span: scope.span,
+                            duration: instant::now() - start,
+                            failure,
+                        });
+                    }
+                }
+                item => {
+                    res = self.catch_crash(|env| env.items(vec![item], false));
+                    if res.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        if path.is_some() {
+            self.current_imports.lock().pop();
+        }
+        res.map(|_| cases)
+    }
+    /// Run a top-level item of a test scope, returning why it failed
+    fn test_item(&mut self, item: Item) -> Option<TestFailure> {
+        let start_height = self.stack.len();
+        let failure = match item {
+            Item::Words(words) => match assertion_operands(&words) {
+                Some(operands) => {
+                    let span = words_span(&words).unwrap();
+                    let assertion = words[..operands].to_vec();
+                    self.test_assertion(assertion, words[operands..].to_vec(), span)
+                }
+                None => self
+                    .catch_crash(|env| env.items(vec![Item::Words(words)], true))
+                    .err()
+                    .map(TestFailure::Error),
+            },
+            item => self
+                .catch_crash(|env| env.items(vec![item], true))
+                .err()
+                .map(TestFailure::Error),
+        };
+        if failure.is_some() {
+            self.stack.truncate(start_height);
+        }
+        failure
+    }
+    /// Run a `⍤⊃⋅∘≍` assertion, keeping the values it compares
+    fn test_assertion(
+        &mut self,
+        assertion: Vec<Sp<Word>>,
+        operands: Vec<Sp<Word>>,
+        span: CodeSpan,
+    ) -> Option<TestFailure> {
+        let res = self.catch_crash(|env| {
+            let instrs = env.compile_words(operands, true)?;
+            env.exec_global_instrs(instrs)
+        });
+        if let Err(error) = res {
+            return Some(TestFailure::Error(error));
+        }
+        if self.stack.len() < 2 {
+            return self
+                .catch_crash(|env| {
+                    let instrs = env.compile_words(assertion, true)?;
+                    env.exec_global_instrs(instrs)
+                })
+                .err()
+                .map(TestFailure::Error);
+        }
+        let expected = self.stack.pop().unwrap();
+        let actual = self.stack.pop().unwrap();
+        (expected != actual).then_some(TestFailure::Mismatch {
+            expected,
+            actual,
+            span,
+        })
+    }
+    fn catch_crash(&mut self, f: impl FnOnce(&mut Self) -> UiuaResult) -> UiuaResult {
+        self.execution_start = instant::now();
+        catch_unwind(AssertUnwindSafe(|| f(self)))
+            .unwrap_or_else(|_| Err(self.error("The interpreter has crashed!")))
+    }
+}
+
+/// Get the name of a test scope from its first comment line
+fn scope_name(scope: &Sp<Vec<Item>>) -> String {
+    for item in &scope.value {
+        match item {
+            Item::Words(words) if words.iter().all(|word| !word.value.is_code()) => {
+                if let Some(comment) = words.iter().find_map(|word| match &word.value {
+                    Word::Comment(comment) => Some(comment.trim()),
+                    _ => None,
+                }) {
+                    if !comment.is_empty() {
+                        return comment.into();
+                    }
+                }
+            }
+            Item::ExtraNewlines(_) => {}
+            _ => break,
+        }
+    }
+    match &scope.span.path {
+        Some(path) => format!("{}:{}", path.display(), scope.span.start.line),
+        None => format!("line {}", scope.span.start.line),
+    }
+}
+
+/// Get the first line of a span's code, marking where the rest is cut off
+fn first_line(span: &CodeSpan) -> String {
+    let code = span.as_str();
+    let mut lines = code.trim().lines();
+    let first = lines.next().unwrap_or_default().trim_end();
+    if lines.next().is_some() {
+        format!("{first} …")
+    } else {
+        first.into()
+    }
+}
+
+/// Check if a filter is a line number within a span
+fn covers_line(span: &CodeSpan, filter: &str) -> bool {
+    filter
+        .parse::<usize>()
+        .is_ok_and(|line| (span.start.line..=span.end.line).contains(&line))
+}
+
+/// Get the span of a line's code words
+fn words_span(words: &[Sp<Word>]) -> Option<CodeSpan> {
+    let mut code = words.iter().filter(|word| word.value.is_code());
+    let first = code.next()?.span.clone();
+    Some(match code.next_back() {
+        Some(last) => first.merge(last.span.clone()),
+        None => first,
+    })
+}
+
+/// If a line starts with `⍤⊃⋅∘≍`, get the index of the words after it
+fn assertion_operands(words: &[Sp<Word>]) -> Option<usize> {
+    fn is_prim(word: &Word, prim: Primitive) -> bool {
+        matches!(word, Word::Primitive(p) if *p == prim)
+    }
+    fn modified(word: &Word, prim: Primitive) -> Option<Vec<&Word>> {
+        match word {
+            Word::Modified(m) if m.modifier.value == Modifier::Primitive(prim) => Some(
+                (m.operands.iter())
+                    .map(|word| &word.value)
+                    .filter(|word| word.is_code())
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+    let mut code = (words.iter().enumerate()).filter(|(_, word)| word.value.is_code());
+    let (_, assert) = code.next()?;
+    let (i, fork) = code.next()?;
+    if !is_prim(&assert.value, Primitive::Assert) {
+        return None;
+    }
+    let [gap, matches] = modified(&fork.value, Primitive::Fork)?[..] else {
+        return None;
+    };
+    let [identity] = modified(gap, Primitive::Gap)?[..] else {
+        return None;
+    };
+    (is_prim(identity, Primitive::Identity) && is_prim(matches, Primitive::Match)).then_some(i + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn per_line_names() {
+        let code = "---\n⍤⊃⋅∘≍ 3 +1 2\n⍤⊃⋅∘≍ [1 2] [1\n2]\n---";
+        let options = TestOptions {
+            per_line: true,
+            ..Default::default()
+        };
+        let cases = (Uiua::with_native_sys().run_tests(code, None, &options)).unwrap();
+        let names: Vec<&str> = cases.iter().map(|case| case.name.as_str()).collect();
+        assert_eq!(names, ["line 1: ⍤⊃⋅∘≍ 3 +1 2", "line 1: ⍤⊃⋅∘≍ [1 2] [1 …"]);
+        assert!(cases.iter().all(TestCase::passed));
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
-    mem::take,
+    mem::{size_of, take},
 };
 
 use ecow::EcoVec;
@@ -209,6 +209,26 @@ impl Value {
             Self::Box(array) => array.first_dim_zero().into(),
         }
     }
+    /// Get the size in bytes of each element
+    pub(crate) fn element_size(&self) -> usize {
+        match self {
+            Self::Num(_) => size_of::<f64>(),
+            #[cfg(feature = "bytes")]
+            Self::Byte(_) => size_of::<u8>(),
+            Self::Complex(_) => size_of::<Complex>(),
+            Self::Char(_) => size_of::<char>(),
+            Self::Box(_) => size_of::<Boxed>(),
+        }
+    }
+    /// Get the number of bytes used by the elements, including the values in boxes
+    pub(crate) fn byte_size(&self) -> usize {
+        let size = self.element_count().saturating_mul(self.element_size());
+        match self {
+            Self::Box(array) => (array.data.iter())
+                .fold(size, |acc, Boxed(val)| acc.saturating_add(val.byte_size())),
+            _ => size,
+        }
+    }
     /// Get a formattable representation of the shape
     pub fn format_shape(&self) -> FormatShape {
         FormatShape(self.shape())
@@ -1376,6 +1396,8 @@ pub(crate) struct ValueBuilder {
     value: Option<Value>,
     rows: usize,
     capacity: usize,
+    /// The bytes in use when the first row was added, plus the bytes of the rows
+    in_use: usize,
 }
 
 impl ValueBuilder {
@@ -1384,12 +1406,16 @@ impl ValueBuilder {
             value: None,
             rows: 0,
             capacity,
+            in_use: 0,
         }
     }
     pub fn add_row<C: FillContext>(&mut self, mut row: Value, ctx: &C) -> Result<(), C::Error> {
         if let Some(value) = &mut self.value {
+            self.in_use = self.in_use.saturating_add(row.byte_size());
+            ctx.check_memory_in_use(self.in_use)?;
             value.append(row, ctx)?;
         } else {
+            self.in_use = ctx.memory_in_use().saturating_add(row.byte_size());
             row.reserve_min(self.capacity);
             row.shape_mut().insert(0, 1);
             self.value = Some(row);
@@ -0,0 +1,16 @@
+use uiua::*;
+
+#[test]
+fn memory_sys_files() {
+    let path = "tests/files.ua";
+    let mut env = Uiua::with_backend(MemorySys::new());
+    if let Err(e) = env.load_file(path) {
+        panic!("Test failed in {path}:\n{}", e.report());
+    } else if let Some(diag) = env.take_diagnostics().into_iter().next() {
+        panic!("Test failed in {path}:\n{}", diag.report());
+    }
+    let sys = env.downcast_backend::<MemorySys>().unwrap();
+    assert!(!sys.file_exists("data.txt"));
+    assert!(sys.file_exists("lines.txt"));
+    assert!(!std::path::Path::new("lines.txt").exists());
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "replay")]
+
+use uiua::*;
+
+const PROGRAM: &str = r#"
+&fras "data.txt"
+&fe "missing.txt"
+&var "HOME"
+⚂
+now
+"#;
+
+fn record(code: &str) -> (Vec<Value>, SysTranscript) {
+    let backend = RecordSys::new(MemorySys::new().with_file("data.txt", "recorded"));
+    let mut env = Uiua::with_backend(backend);
+    env.load_str(code).unwrap();
+    let transcript = (env.downcast_backend::<RecordSys<MemorySys>>())
+        .unwrap()
+        .transcript();
+    (env.take_stack(), transcript)
+}
+
+#[test]
+fn record_then_replay() {
+    let (recorded, transcript) = record(PROGRAM);
+    let transcript = SysTranscript::from_json(&transcript.to_json()).unwrap();
+
+    let mut env = Uiua::with_backend(ReplaySys::new(transcript));
+    env.load_str(PROGRAM).unwrap();
+    assert_eq!(env.take_stack(), recorded);
+
+    let replay = env.downcast_backend::<ReplaySys>().unwrap();
+    assert_eq!(replay.remaining(), 0);
+    assert_eq!(replay.divergence(), None);
+}
+
+#[test]
+fn replay_fails_after_divergence() {
+    let (_, transcript) = record(PROGRAM);
+
+    // Random numbers cannot fail, so the divergence is reported by the next system function
+    let mut env = Uiua::with_backend(ReplaySys::new(transcript));
+    env.load_str("⚂").unwrap();
+    let replay = env.downcast_backend::<ReplaySys>().unwrap();
+    let divergence = replay.divergence().unwrap();
+    assert!(
+        divergence.contains("Replay diverged at call 1"),
+        "{divergence}"
+    );
+
+    let error = env.load_str("&p \"hi\"").unwrap_err().to_string();
+    assert!(error.contains("Replay diverged at call 1"), "{error}");
+    let error = env.load_str(r#"&fras "data.txt""#).unwrap_err().to_string();
+    assert!(error.contains("Replay diverged at call 1"), "{error}");
+}
+
+#[test]
+fn replay_fails_when_transcript_ends() {
+    let (_, transcript) = record("⚂");
+
+    let mut env = Uiua::with_backend(ReplaySys::new(transcript));
+    let error = env
+        .load_str(r#"&fras "data.txt" ⚂ ⚂"#)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("the transcript has ended"), "{error}");
+}